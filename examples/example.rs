@@ -1,26 +1,10 @@
-use gouache::{Color, Vec2, Mat2x2, PathBuilder, Frame, Font, Cache, renderers::GlRenderer};
+use gouache::{Color, Vec2, Transform, PathBuilder, Frame, Font, LayoutOptions, Cache, renderers::GlRenderer};
 
 const FRAME: std::time::Duration = std::time::Duration::from_micros(1_000_000 / 60);
 
-const TEXT: &'static str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor
-incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis
-nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.
-Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu
-fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in
-culpa qui officia deserunt mollit anim id est laborum.
-
-Curabitur pretium tincidunt lacus. Nulla gravida orci a odio. Nullam varius,
-turpis et commodo pharetra, est eros bibendum elit, nec luctus magna felis
-sollicitudin mauris. Integer in mauris eu nibh euismod gravida. Duis ac tellus
-et risus vulputate vehicula. Donec lobortis risus a elit. Etiam tempor. Ut
-ullamcorper, ligula eu tempor congue, eros est euismod turpis, id tincidunt
-sapien risus a quam. Maecenas fermentum consequat mi. Donec fermentum.
-Pellentesque malesuada nulla a mi. Duis sapien sem, aliquet nec, commodo eget,
-consequat quis, neque. Aliquam faucibus, elit ut dictum aliquet, felis nisl
-adipiscing sapien, sed malesuada diam lacus eget erat. Cras mollis scelerisque
-nunc. Nullam arcu. Aliquam consequat. Curabitur augue lorem, dapibus quis,
-laoreet et, pretium ac, nisi. Aenean magna nisl, mollis quis, molestie eu,
-feugiat in, orci. In hac habitasse platea dictumst.";
+const TEXT: &'static str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.
+
+Curabitur pretium tincidunt lacus. Nulla gravida orci a odio. Nullam varius, turpis et commodo pharetra, est eros bibendum elit, nec luctus magna felis sollicitudin mauris. Integer in mauris eu nibh euismod gravida. Duis ac tellus et risus vulputate vehicula. Donec lobortis risus a elit. Etiam tempor. Ut ullamcorper, ligula eu tempor congue, eros est euismod turpis, id tincidunt sapien risus a quam. Maecenas fermentum consequat mi. Donec fermentum. Pellentesque malesuada nulla a mi. Duis sapien sem, aliquet nec, commodo eget, consequat quis, neque. Aliquam faucibus, elit ut dictum aliquet, felis nisl adipiscing sapien, sed malesuada diam lacus eget erat. Cras mollis scelerisque nunc. Nullam arcu. Aliquam consequat. Curabitur augue lorem, dapibus quis, laoreet et, pretium ac, nisi. Aenean magna nisl, mollis quis, molestie eu, feugiat in, orci. In hac habitasse platea dictumst.";
 
 fn main() {
     let mut events_loop = glutin::EventsLoop::new();
@@ -45,7 +29,8 @@ fn main() {
         .line_to(1.0, 0.5)
         .line_to(0.5, 0.0)
         .line_to(0.0, 0.5)
-        .build();
+        .build()
+        .unwrap();
     let path_key = cache.add_path();
 
     let text = font.layout("jackdaws love my big sphinx of quartz 1234567890", 14.0);
@@ -55,7 +40,9 @@ fn main() {
     let mut angle = 0.0;
 
     let center = 0.5 * Vec2::new(800.0, 600.0);
-    let (width, height) = font.measure(TEXT, 14.0);
+    let mut options = LayoutOptions::new();
+    options.max_width = Some(500.0);
+    let (width, height) = font.measure_with(TEXT, 14.0, &options);
     let text_center = 0.5 * Vec2::new(width, height);
 
     let mut running = true;
@@ -71,8 +58,8 @@ fn main() {
             angle -= 0.05;
         }
 
-        let transform = Mat2x2::scale(size) * Mat2x2::rotate(angle);
-        frame.draw_text(&font, font_key, 14.0, TEXT, center - transform * text_center, transform, Color::rgba(0.1, 0.05, 0.1, 1.0));
+        let transform = Transform::translate(center.x, center.y) * Transform::scale(size, size) * Transform::rotate(angle) * Transform::translate(-text_center.x, -text_center.y);
+        frame.draw_text_with(&font, font_key, 14.0, TEXT, &options, transform, Color::rgba(0.1, 0.05, 0.1, 1.0)).unwrap();
         frame.finish();
 
         context.swap_buffers().unwrap();
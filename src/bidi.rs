@@ -0,0 +1,171 @@
+use crate::geom::*;
+use crate::font::*;
+use crate::wrap::*;
+
+use unicode_bidi::BidiInfo;
+
+impl<'a> Font<'a> {
+    /* glyphs of a line in visual order, with runs reordered by the bidirectional algorithm and moved into place
+    for the alignment, along with where the line starts; clusters are byte offsets into the whole text */
+    pub(crate) fn place_line(&self, text: &str, bidi: &BidiInfo, line: Line, width: f32, size: f32, options: &LayoutOptions) -> (Vec<ShapedGlyph>, f32) {
+        let paragraph = bidi.paragraphs.iter().find(|paragraph| paragraph.range.start <= line.start && line.start < paragraph.range.end);
+        let mut glyphs = Vec::new();
+        let mut pen = 0.0;
+        if let (Some(paragraph), true) = (paragraph, line.start < line.end) {
+            let (levels, runs) = bidi.visual_runs(paragraph, line.start..line.end);
+            for run in runs {
+                let (shaped, advance) = self.shape_line(&text[run.clone()], levels[run.start].is_rtl(), options);
+                glyphs.extend(shaped.into_iter().map(|glyph| ShapedGlyph { x: pen + glyph.x, cluster: run.start + glyph.cluster, ..glyph }));
+                pen += advance;
+            }
+        }
+
+        /* spaces between words, which take up the slack of justified lines */
+        let content = line.start + text[line.start..line.end].trim_end().len();
+        let is_space = |cluster: usize| cluster < content && text[cluster..].chars().next().is_some_and(char::is_whitespace);
+        let spaces = text[line.start..content].chars().filter(|c| c.is_whitespace()).count();
+
+        let slack = (width - line.width).max(0.0) / self.scale(size);
+        let (shift, stretch) = match options.alignment {
+            Alignment::Left => (0.0, 0.0),
+            Alignment::Right => (slack, 0.0),
+            Alignment::Center => (0.5 * slack, 0.0),
+            Alignment::Justify if line.wrapped && spaces > 0 => (0.0, slack / spaces as f32),
+            Alignment::Justify => (0.0, 0.0),
+        };
+
+        let mut extra = shift;
+        for glyph in glyphs.iter_mut() {
+            glyph.x += extra;
+            if is_space(glyph.cluster) {
+                glyph.advance += stretch;
+                extra += stretch;
+            }
+        }
+
+        (glyphs, shift)
+    }
+
+    /* point on the baseline where the caret goes before the character at the byte index,
+    on the leading side of that character in its direction */
+    pub fn caret_position(&self, text: &str, size: f32, options: &LayoutOptions, index: usize) -> Vec2 {
+        /* an index inside a character is taken as the start of that character */
+        let index = (0..=index.min(text.len())).rev().find(|&index| text.is_char_boundary(index)).unwrap_or(0);
+        let lines = self.lines(text, size, options);
+        let width = options.max_width.unwrap_or_else(|| lines.iter().fold(0.0f32, |width, line| width.max(line.width)));
+        let bidi = BidiInfo::new(text, None);
+
+        let row = lines.iter().rposition(|line| line.start <= index).unwrap_or(0);
+        let line = lines[row];
+        let (glyphs, start) = self.place_line(text, &bidi, line, width, size, options);
+        let x = caret_x(text, line, &glyphs, start, index.min(line.end));
+        Vec2::new(self.scale(size) * x, self.baseline(size, row))
+    }
+
+    /* byte index of the caret position nearest to a point in laid out text */
+    pub fn caret_index(&self, text: &str, size: f32, options: &LayoutOptions, point: Vec2) -> usize {
+        let lines = self.lines(text, size, options);
+        let width = options.max_width.unwrap_or_else(|| lines.iter().fold(0.0f32, |width, line| width.max(line.width)));
+        let bidi = BidiInfo::new(text, None);
+
+        let line_height = self.baseline(size, 1) - self.baseline(size, 0);
+        let row = ((point.y / line_height).max(0.0) as usize).min(lines.len() - 1);
+        let line = lines[row];
+        let (glyphs, start) = self.place_line(text, &bidi, line, width, size, options);
+        let x = point.x / self.scale(size);
+
+        /* the end of a wrapped line is the start of the next one */
+        let mut best = line.start;
+        let mut best_distance = std::f32::INFINITY;
+        let boundaries = text[line.start..line.end].char_indices().map(|(i, _)| line.start + i);
+        for index in boundaries.chain(if line.wrapped { None } else { Some(line.end) }) {
+            let distance = (caret_x(text, line, &glyphs, start, index) - x).abs();
+            if distance < best_distance {
+                best = index;
+                best_distance = distance;
+            }
+        }
+        best
+    }
+}
+
+/* caret offset in font units, dividing the glyphs of a cluster like a ligature evenly among its characters */
+fn caret_x(text: &str, line: Line, glyphs: &[ShapedGlyph], start: f32, index: usize) -> f32 {
+    /* visual extent and direction of each cluster, in logical order */
+    let mut clusters: Vec<(usize, f32, f32, bool)> = Vec::new();
+    for glyph in glyphs {
+        if let Some(cluster) = clusters.iter_mut().find(|cluster| cluster.0 == glyph.cluster) {
+            cluster.1 = cluster.1.min(glyph.x);
+            cluster.2 = cluster.2.max(glyph.x + glyph.advance);
+        } else {
+            clusters.push((glyph.cluster, glyph.x, glyph.x + glyph.advance, glyph.rtl));
+        }
+    }
+    clusters.sort_by_key(|cluster| cluster.0);
+
+    let k = match clusters.iter().rposition(|cluster| cluster.0 <= index) {
+        Some(k) => k,
+        None => match clusters.first() {
+            Some(&(_, left, right, rtl)) => return if rtl { right } else { left },
+            None => return start,
+        },
+    };
+    let (cluster, left, right, rtl) = clusters[k];
+    let end = clusters.get(k + 1).map_or(line.end, |next| next.0);
+    let total = text[cluster..end].chars().count().max(1);
+    let before = text[cluster..index.min(end)].chars().count();
+    let (leading, trailing) = if rtl { (right, left) } else { (left, right) };
+    leading + (trailing - leading) * before as f32 / total as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] = include_bytes!("../res/SourceSansPro-Regular.ttf");
+    /* the font has no right-to-left script, so an override makes latin letters run right to left */
+    const TEXT: &str = "ab \u{202E}cdé\u{202C} ef";
+
+    fn keys(font: &Font, text: &str) -> Vec<u16> {
+        let mut glyphs: Vec<Glyph> = font.layout(text, 16.0).collect();
+        glyphs.sort_by(|a, b| a.position.x.partial_cmp(&b.position.x).unwrap());
+        glyphs.into_iter().map(|glyph| glyph.glyph_key.0).collect()
+    }
+
+    #[test]
+    fn visual_order() {
+        let font = Font::from_bytes(FONT).unwrap();
+        assert_eq!(keys(&font, TEXT), keys(&font, "ab édc ef"));
+    }
+
+    #[test]
+    fn caret_round_trip() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let options = LayoutOptions::new();
+        for (index, _) in TEXT.char_indices() {
+            let position = font.caret_position(TEXT, 16.0, &options, index);
+            let nearest = font.caret_index(TEXT, 16.0, &options, position);
+            assert_eq!(font.caret_position(TEXT, 16.0, &options, nearest), position);
+        }
+
+        /* carets within a run are unambiguous */
+        for &index in &[1, 2, 7, 8, 15] {
+            let position = font.caret_position(TEXT, 16.0, &options, index);
+            assert_eq!(font.caret_index(TEXT, 16.0, &options, position), index);
+        }
+
+        /* right to left, so the caret before d is left of the one before c */
+        let c = font.caret_position(TEXT, 16.0, &options, 6);
+        let d = font.caret_position(TEXT, 16.0, &options, 7);
+        assert!(d.x < c.x);
+    }
+
+    #[test]
+    fn caret_inside_character() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let options = LayoutOptions::new();
+        let e = TEXT.find('é').unwrap();
+        assert_eq!(font.caret_position(TEXT, 16.0, &options, e + 1), font.caret_position(TEXT, 16.0, &options, e));
+        assert_eq!(font.caret_position(TEXT, 16.0, &options, 4), font.caret_position(TEXT, 16.0, &options, 3));
+    }
+}
@@ -0,0 +1,284 @@
+use crate::geom::*;
+use crate::path::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+impl PathBuilder {
+    pub fn boolean(&self, other: &PathBuilder, op: BooleanOp) -> PathBuilder {
+        let a = monotone(&self.fill_segments());
+        let b = monotone(&other.fill_segments());
+
+        let mut min = Vec2::new(std::f32::INFINITY, std::f32::INFINITY);
+        let mut max = Vec2::new(-std::f32::INFINITY, -std::f32::INFINITY);
+        for segment in a.iter().chain(b.iter()) {
+            min = min.min(segment.p1).min(segment.p2).min(segment.p3);
+            max = max.max(segment.p1).max(segment.p2).max(segment.p3);
+        }
+        let extent = (max - min).x.max((max - min).y).max(1.0e-6);
+        let tolerance = 1.0e-5 * extent;
+
+        let segments: Vec<Segment> = a.iter().chain(b.iter()).cloned().collect();
+        let mut splits = vec![Vec::new(); segments.len()];
+        for i in 0..segments.len() {
+            for j in i + 1..segments.len() {
+                let mut intersections = Vec::new();
+                intersect(&segments[i], (0.0, 1.0), &segments[j], (0.0, 1.0), tolerance, 0, &mut intersections);
+                for (t1, t2) in intersections {
+                    splits[i].push(t1);
+                    splits[j].push(t2);
+                }
+            }
+        }
+
+        /* keep every piece of the boundary that separates the result from its complement,
+        oriented so that the result lies on its left */
+        let offset = 1.0e-4 * extent;
+        let inside = |point: Vec2| {
+            op.apply(self.fill_rule.contains(winding(&a, point)), other.fill_rule.contains(winding(&b, point)))
+        };
+        let mut pieces: Vec<Segment> = Vec::new();
+        for (segment, splits) in segments.iter().zip(splits.iter_mut()) {
+            splits.retain(|&t| t > 1.0e-4 && t < 1.0 - 1.0e-4);
+            splits.sort_by(|t1, t2| t1.partial_cmp(t2).unwrap_or(std::cmp::Ordering::Equal));
+            splits.dedup_by(|t1, t2| (*t1 - *t2).abs() < 1.0e-4);
+
+            let mut t1 = 0.0;
+            for &t2 in splits.iter().chain(std::iter::once(&1.0)) {
+                let piece = segment.split_range(t1, t2);
+                t1 = t2;
+
+                let tangent = piece.derivative(0.5);
+                if tangent.length() < 1.0e-12 {
+                    continue;
+                }
+                let normal = offset * Vec2::new(-tangent.y, tangent.x).normalized();
+                let middle = piece.point(0.5);
+                let left = inside(middle + normal);
+                let right = inside(middle - normal);
+                if left && !right {
+                    pieces.push(piece);
+                } else if right && !left {
+                    pieces.push(piece.reversed());
+                }
+            }
+        }
+
+        /* coincident edges of both operands would otherwise be counted twice */
+        let same = |s1: &Segment, s2: &Segment| {
+            s1.p1.distance(s2.p1) < 1.0e-3 * extent && s1.p2.distance(s2.p2) < 1.0e-3 * extent && s1.p3.distance(s2.p3) < 1.0e-3 * extent
+        };
+        let mut unique: Vec<Segment> = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            let reversed = piece.reversed();
+            if let Some(i) = unique.iter().position(|other| same(other, &reversed)) {
+                unique.swap_remove(i);
+            } else if !unique.iter().any(|other| same(other, &piece)) {
+                unique.push(piece);
+            }
+        }
+
+        let mut builder = PathBuilder::new();
+        builder.tolerance(self.tolerance).precision(self.precision);
+        chain(&unique, 1.0e-3 * extent, &mut builder);
+        builder
+    }
+}
+
+/* links pieces end to start into contours */
+fn chain(pieces: &[Segment], tolerance: f32, builder: &mut PathBuilder) {
+    let mut used = vec![false; pieces.len()];
+    for start in 0..pieces.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let first = pieces[start].p1;
+        builder.move_to(first.x, first.y);
+        builder.quadratic_to(pieces[start].p2.x, pieces[start].p2.y, pieces[start].p3.x, pieces[start].p3.y);
+        let mut last = pieces[start].p3;
+
+        loop {
+            if last.distance(first) < tolerance {
+                break;
+            }
+
+            let mut next = None;
+            let mut next_distance = tolerance;
+            for (i, piece) in pieces.iter().enumerate() {
+                let distance = piece.p1.distance(last);
+                if !used[i] && distance < next_distance {
+                    next = Some(i);
+                    next_distance = distance;
+                }
+            }
+
+            if let Some(i) = next {
+                used[i] = true;
+                builder.quadratic_to(pieces[i].p2.x, pieces[i].p2.y, pieces[i].p3.x, pieces[i].p3.y);
+                last = pieces[i].p3;
+            } else {
+                break;
+            }
+        }
+
+        builder.close();
+    }
+}
+
+fn intersect(s1: &Segment, range1: (f32, f32), s2: &Segment, range2: (f32, f32), tolerance: f32, depth: u32, out: &mut Vec<(f32, f32)>) {
+    const MAX_DEPTH: u32 = 24;
+
+    let (min1, max1) = bounds(s1);
+    let (min2, max2) = bounds(s2);
+    if min1.x > max2.x + tolerance || min2.x > max1.x + tolerance || min1.y > max2.y + tolerance || min2.y > max1.y + tolerance {
+        return;
+    }
+
+    /* a shared edge, like the same curve in both operands, is only split where it ends,
+    since subdividing it would find its chords overlapping everywhere */
+    let coincident = |s1: &Segment, s2: &Segment| {
+        s1.p1.distance(s2.p1) < tolerance && s1.p2.distance(s2.p2) < tolerance && s1.p3.distance(s2.p3) < tolerance
+    };
+    if coincident(s1, s2) {
+        out.extend_from_slice(&[(range1.0, range2.0), (range1.1, range2.1)]);
+        return;
+    }
+    if coincident(s1, &s2.reversed()) {
+        out.extend_from_slice(&[(range1.0, range2.1), (range1.1, range2.0)]);
+        return;
+    }
+
+    let flat1 = flatness(s1) < tolerance;
+    let flat2 = flatness(s2) < tolerance;
+    if (flat1 && flat2) || depth >= MAX_DEPTH {
+        let d1 = s1.p3 - s1.p1;
+        let d2 = s2.p3 - s2.p1;
+        let lerp1 = |t: f32| range1.0 + t * (range1.1 - range1.0);
+        let lerp2 = |t: f32| range2.0 + t * (range2.1 - range2.0);
+
+        let denom = d1.cross(d2);
+        if denom.abs() > 1.0e-6 * d1.length() * d2.length() {
+            let t1 = (s2.p1 - s1.p1).cross(d2) / denom;
+            let t2 = (s2.p1 - s1.p1).cross(d1) / denom;
+            if (0.0..=1.0).contains(&t1) && (0.0..=1.0).contains(&t2) {
+                out.push((lerp1(t1), lerp2(t2)));
+            }
+        } else if d1.length() > 0.0 && d2.length() > 0.0 && (s2.p1 - s1.p1).cross(d1).abs() < tolerance * d1.length() {
+            /* collinear overlap: split each chord where the other one ends */
+            for &(point, t2) in &[(s2.p1, 0.0), (s2.p3, 1.0)] {
+                let t1 = (point - s1.p1).dot(d1) / d1.dot(d1);
+                if t1 > 0.0 && t1 < 1.0 {
+                    out.push((lerp1(t1), lerp2(t2)));
+                }
+            }
+            for &(point, t1) in &[(s1.p1, 0.0), (s1.p3, 1.0)] {
+                let t2 = (point - s2.p1).dot(d2) / d2.dot(d2);
+                if t2 > 0.0 && t2 < 1.0 {
+                    out.push((lerp1(t1), lerp2(t2)));
+                }
+            }
+        }
+        return;
+    }
+
+    let mid1 = 0.5 * (range1.0 + range1.1);
+    let mid2 = 0.5 * (range2.0 + range2.1);
+    if flat1 {
+        let (left2, right2) = s2.split_at(0.5);
+        intersect(s1, range1, &left2, (range2.0, mid2), tolerance, depth + 1, out);
+        intersect(s1, range1, &right2, (mid2, range2.1), tolerance, depth + 1, out);
+    } else if flat2 {
+        let (left1, right1) = s1.split_at(0.5);
+        intersect(&left1, (range1.0, mid1), s2, range2, tolerance, depth + 1, out);
+        intersect(&right1, (mid1, range1.1), s2, range2, tolerance, depth + 1, out);
+    } else {
+        let (left1, right1) = s1.split_at(0.5);
+        let (left2, right2) = s2.split_at(0.5);
+        intersect(&left1, (range1.0, mid1), &left2, (range2.0, mid2), tolerance, depth + 1, out);
+        intersect(&left1, (range1.0, mid1), &right2, (mid2, range2.1), tolerance, depth + 1, out);
+        intersect(&right1, (mid1, range1.1), &left2, (range2.0, mid2), tolerance, depth + 1, out);
+        intersect(&right1, (mid1, range1.1), &right2, (mid2, range2.1), tolerance, depth + 1, out);
+    }
+}
+
+fn bounds(segment: &Segment) -> (Vec2, Vec2) {
+    (
+        segment.p1.min(segment.p2).min(segment.p3),
+        segment.p1.max(segment.p2).max(segment.p3),
+    )
+}
+
+/* distance of the control point from the chord midpoint, which bounds the deviation from a line */
+fn flatness(segment: &Segment) -> f32 {
+    (segment.p2 - 0.5 * (segment.p1 + segment.p3)).length()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle() -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        builder.circle(0.0, 0.0, 10.0);
+        builder
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        builder.rect(x, y, width, height);
+        builder
+    }
+
+    #[test]
+    fn self_union() {
+        let circle = circle();
+        for &op in &[BooleanOp::Union, BooleanOp::Intersection] {
+            let mut result = circle.boolean(&circle, op);
+            assert_eq!(result.segments.len(), circle.segments.len());
+            let path = result.build().unwrap();
+            assert!(path.contains(Vec2::new(0.0, 0.0)));
+            assert!(!path.contains(Vec2::new(9.0, 9.0)));
+        }
+        assert!(circle.boolean(&circle, BooleanOp::Difference).segments.is_empty());
+    }
+
+    #[test]
+    fn shared_edge() {
+        let mut result = rect(0.0, 0.0, 10.0, 10.0).boolean(&rect(10.0, 0.0, 10.0, 10.0), BooleanOp::Union);
+        assert_eq!(result.contours().count(), 1);
+        assert!(result.segments.iter().all(|segment| segment.p1.x != 10.0 || segment.p3.x != 10.0));
+        let path = result.build().unwrap();
+        assert!(path.contains(Vec2::new(10.0, 5.0)));
+        assert_eq!(path.bounds(), Rect::new(Vec2::new(0.0, 0.0), Vec2::new(20.0, 10.0)));
+    }
+
+    #[test]
+    fn circle_rect_intersection() {
+        let mut result = circle().boolean(&rect(0.0, -20.0, 20.0, 40.0), BooleanOp::Intersection);
+        let path = result.build().unwrap();
+        assert!(path.contains(Vec2::new(5.0, 0.0)));
+        assert!(!path.contains(Vec2::new(-5.0, 0.0)));
+        assert!(!path.contains(Vec2::new(9.0, 9.0)));
+        let bounds = path.bounds();
+        assert!(bounds.min.x.abs() < 1.0e-3 && (bounds.max.x - 10.0).abs() < 1.0e-3);
+        assert!((bounds.min.y + 10.0).abs() < 1.0e-3 && (bounds.max.y - 10.0).abs() < 1.0e-3);
+    }
+}
@@ -1,27 +1,119 @@
 use crate::geom::*;
 use crate::path::*;
+use crate::wrap::*;
 use crate::frame::Cache;
+#[cfg(not(feature = "shaping"))]
+use crate::kerning::Kerning;
 
 use std::collections::HashMap;
+use std::fmt;
 pub use ttf_parser::Error as FontError;
 use ttf_parser::GlyphId;
+use unicode_bidi::BidiInfo;
 
 pub struct Font<'a> {
+    pub(crate) data: &'a [u8],
     font: ttf_parser::Font<'a>,
-    glyphs: HashMap<GlyphKey, Path>,
+    #[cfg(feature = "shaping")]
+    face: rustybuzz::Face<'a>,
+    #[cfg(not(feature = "shaping"))]
+    kerning: Option<Kerning<'a>>,
+    /* outlines loaded ahead of time, returned by build_glyph instead of building from the font */
+    pub(crate) glyphs: HashMap<GlyphKey, Path>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
-pub struct GlyphKey(u16);
+pub struct GlyphKey(pub(crate) u16);
+
+#[derive(Copy, Clone, Debug)]
+pub enum GlyphError {
+    /* the glyph has no outline, like a space, or the font's outline data is malformed */
+    Outline(FontError),
+    Path(PathError),
+}
+
+impl fmt::Display for GlyphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GlyphError::Outline(error) => write!(f, "{}", error),
+            GlyphError::Path(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for GlyphError {}
+
+/* a glyph of a shaped run in font units */
+#[derive(Copy, Clone)]
+pub(crate) struct ShapedGlyph {
+    pub(crate) glyph_key: GlyphKey,
+    /* start of the glyph's advance along the baseline, and where it is drawn relative to that */
+    pub(crate) x: f32,
+    pub(crate) offset: Vec2,
+    pub(crate) advance: f32,
+    /* byte offset of the text the glyph comes from */
+    pub(crate) cluster: usize,
+    pub(crate) rtl: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+    /* stretches the spaces of wrapped lines to the full width, leaving the last line of each paragraph flush left */
+    Justify,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LayoutOptions {
+    /* pair kerning from GPOS, or from the kern table for fonts without it */
+    pub kerning: bool,
+    /* width in pixels at which lines are wrapped */
+    pub max_width: Option<f32>,
+    /* lines are aligned within max_width, or within the widest line without it */
+    pub alignment: Alignment,
+}
+
+impl LayoutOptions {
+    pub fn new() -> LayoutOptions {
+        LayoutOptions { kerning: true, max_width: None, alignment: Alignment::Left }
+    }
+}
+
+impl Default for LayoutOptions {
+    fn default() -> LayoutOptions {
+        LayoutOptions::new()
+    }
+}
 
 impl<'a> Font<'a> {
     pub fn from_bytes(bytes: &'a [u8]) -> Result<Font<'a>, FontError> {
-        ttf_parser::Font::from_data(bytes, 0).map(|font| Font { font, glyphs: HashMap::new() })
+        let font = ttf_parser::Font::from_data(bytes, 0)?;
+        #[cfg(feature = "shaping")]
+        let face = rustybuzz::Face::from_slice(bytes, 0).ok_or(FontError::NotATrueType)?;
+        Ok(Font {
+            data: bytes,
+            font,
+            #[cfg(feature = "shaping")]
+            face,
+            #[cfg(not(feature = "shaping"))]
+            kerning: Kerning::new(bytes),
+            glyphs: HashMap::new(),
+        })
     }
 
-    pub fn build_glyph(&self, glyph: GlyphKey) -> Path {
+    pub fn glyph_key(&self, c: char) -> Option<GlyphKey> {
+        self.font.glyph_index(c).ok().map(|glyph_id| GlyphKey(glyph_id.0))
+    }
+
+    pub fn build_glyph(&self, glyph: GlyphKey) -> Result<Path, GlyphError> {
         use ttf_parser::OutlineBuilder;
 
+        if let Some(path) = self.glyphs.get(&glyph) {
+            return Ok(path.clone());
+        }
+
         struct Builder { path: PathBuilder }
         impl OutlineBuilder for Builder {
             fn move_to(&mut self, x: f32, y: f32) {
@@ -33,51 +125,134 @@ impl<'a> Font<'a> {
             fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
                 self.path.quadratic_to(x1, -y1, x, -y);
             }
-            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {}
-            fn close(&mut self) {}
+            fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+                self.path.cubic_to(x1, -y1, x2, -y2, x, -y);
+            }
+            fn close(&mut self) {
+                self.path.close();
+            }
         }
 
         let mut builder = Builder { path: PathBuilder::new() };
-        self.font.outline_glyph(GlyphId(glyph.0), &mut builder);
-        builder.path.build()
+        self.font.outline_glyph(GlyphId(glyph.0), &mut builder).map_err(GlyphError::Outline)?;
+        builder.path.build().map_err(GlyphError::Path)
     }
 
-    pub fn measure(&self, text: &str, size: f32) -> (f32, f32) {
-        let scale = size / self.font.units_per_em().unwrap() as f32;
-        let mut width: f32 = 0.0;
-        let mut line_width: f32 = 0.0;
-        let mut lines: usize = 0;
-        for c in text.chars() {
-            if c == '\n' {
-                width = width.max(line_width);
-                line_width = 0.0;
-                lines += 1;
-            } else if let Ok(glyph_id) = self.font.glyph_index(c) {
-                line_width += scale * self.font.glyph_hor_metrics(glyph_id).unwrap().advance as f32;
+    /* glyphs of a run of text in one direction, in visual order along the baseline from zero, and the run's advance */
+    #[cfg(not(feature = "shaping"))]
+    pub(crate) fn shape_line(&self, line: &str, rtl: bool, options: &LayoutOptions) -> (Vec<ShapedGlyph>, f32) {
+        let mut glyphs = Vec::new();
+        let mut pen = 0.0;
+        let mut previous = None;
+        for (cluster, c) in line.char_indices() {
+            if let Ok(glyph_id) = self.font.glyph_index(c) {
+                if let (true, Some(previous)) = (options.kerning, previous) {
+                    pen += self.kerning(previous, glyph_id) as f32;
+                }
+                previous = Some(glyph_id);
+                let advance = self.font.glyph_hor_metrics(glyph_id).unwrap().advance as f32;
+                glyphs.push(ShapedGlyph { glyph_key: GlyphKey(glyph_id.0), x: pen, offset: Vec2::new(0.0, 0.0), advance, cluster, rtl });
+                pen += advance;
             }
         }
 
+        /* kerning pairs are in logical order, so right-to-left runs are mirrored only once positioned */
+        if rtl {
+            glyphs.reverse();
+            for glyph in glyphs.iter_mut() {
+                glyph.x = pen - glyph.x - glyph.advance;
+            }
+        }
+        (glyphs, pen)
+    }
+
+    #[cfg(not(feature = "shaping"))]
+    fn kerning(&self, left: GlyphId, right: GlyphId) -> i16 {
+        match self.kerning {
+            Some(ref kerning) => kerning.pair(left.0, right.0),
+            None => self.font.glyphs_kerning(left, right).ok(),
+        }.unwrap_or(0)
+    }
+
+    #[cfg(feature = "shaping")]
+    pub(crate) fn shape_line(&self, line: &str, rtl: bool, options: &LayoutOptions) -> (Vec<ShapedGlyph>, f32) {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(line);
+        buffer.guess_segment_properties();
+        buffer.set_direction(if rtl { rustybuzz::Direction::RightToLeft } else { rustybuzz::Direction::LeftToRight });
+        let mut features = Vec::new();
+        if !options.kerning {
+            features.push(rustybuzz::Feature::new(rustybuzz::ttf_parser::Tag::from_bytes(b"kern"), 0, ..));
+        }
+        let output = rustybuzz::shape(&self.face, &features, buffer);
+
+        let mut glyphs = Vec::with_capacity(output.len());
+        let mut pen = 0.0;
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions().iter()) {
+            /* the shaper's y axis points up */
+            let offset = Vec2::new(position.x_offset as f32, -position.y_offset as f32);
+            let advance = position.x_advance as f32;
+            glyphs.push(ShapedGlyph { glyph_key: GlyphKey(info.glyph_id as u16), x: pen, offset, advance, cluster: info.cluster as usize, rtl });
+            pen += advance;
+        }
+        (glyphs, pen)
+    }
+
+    /* pixels per font unit */
+    pub(crate) fn scale(&self, size: f32) -> f32 {
+        size / self.font.units_per_em().unwrap() as f32
+    }
+
+    /* baseline of the line with the given index, in pixels from the top of the text */
+    pub(crate) fn baseline(&self, size: f32, line: usize) -> f32 {
+        self.scale(size) * (self.font.ascender() as f32 + line as f32 * (self.font.height() + self.font.line_gap()) as f32)
+    }
+
+    pub fn measure(&self, text: &str, size: f32) -> (f32, f32) {
+        self.measure_with(text, size, &LayoutOptions::new())
+    }
+
+    pub fn measure_with(&self, text: &str, size: f32, options: &LayoutOptions) -> (f32, f32) {
+        let scale = self.scale(size);
+        let lines = self.lines(text, size, options);
+        let width = lines.iter().fold(0.0f32, |width, line| width.max(line.width));
+        let lines = lines.len();
+
         (width, scale * (lines as f32 * self.font.height() as f32 + lines.saturating_sub(1) as f32 * self.font.line_gap() as f32))
     }
 
     pub fn layout<'f, 't>(&'f self, text: &'t str, size: f32) -> LayoutIter<'f, 't> {
-        let scale = size / self.font.units_per_em().unwrap() as f32;
-        let origin = Vec2::new(0.0, scale * self.font.ascender() as f32);
+        self.layout_with(text, size, &LayoutOptions::new())
+    }
+
+    pub fn layout_with<'f, 't>(&'f self, text: &'t str, size: f32, options: &LayoutOptions) -> LayoutIter<'f, 't> {
+        let lines = self.lines(text, size, options);
+        let width = options.max_width.unwrap_or_else(|| lines.iter().fold(0.0f32, |width, line| width.max(line.width)));
         LayoutIter {
             font: self,
-            chars: text.chars(),
-            scale,
-            origin,
-            position: origin,
+            text,
+            bidi: BidiInfo::new(text, None),
+            lines: lines.into_iter().enumerate(),
+            width,
+            options: *options,
+            glyphs: Vec::new().into_iter(),
+            size,
+            position: Vec2::new(0.0, 0.0),
         }
     }
 }
 
 pub struct LayoutIter<'f, 'c> {
     font: &'f Font<'f>,
-    chars: std::str::Chars<'c>,
-    scale: f32,
-    origin: Vec2,
+    text: &'c str,
+    bidi: BidiInfo<'c>,
+    lines: std::iter::Enumerate<std::vec::IntoIter<Line>>,
+    /* width in pixels that lines are aligned within */
+    width: f32,
+    options: LayoutOptions,
+    glyphs: std::vec::IntoIter<ShapedGlyph>,
+    size: f32,
+    /* start of the current line's baseline */
     position: Vec2,
 }
 
@@ -85,18 +260,17 @@ impl<'f, 'c> Iterator for LayoutIter<'f, 'c> {
     type Item = Glyph;
 
     fn next(&mut self) -> Option<Glyph> {
-        while let Some(c) = self.chars.next() {
-            if c == '\n' {
-                self.position.x = self.origin.x;
-                self.position.y += self.scale * (self.font.font.height() + self.font.font.line_gap()) as f32;
-            } else if let Ok(glyph_id) = self.font.font.glyph_index(c) {
-                let glyph = Glyph { position: self.position, scale: self.scale, glyph_key: GlyphKey(glyph_id.0) };
-                self.position.x += self.scale * self.font.font.glyph_hor_metrics(glyph_id).unwrap().advance as f32;
-                return Some(glyph);
+        loop {
+            if let Some(glyph) = self.glyphs.next() {
+                let scale = self.font.scale(self.size);
+                let position = self.position + scale * (Vec2::new(glyph.x, 0.0) + glyph.offset);
+                return Some(Glyph { position, scale, glyph_key: glyph.glyph_key });
             }
-        }
 
-        None
+            let (index, line) = self.lines.next()?;
+            self.position.y = self.font.baseline(self.size, index);
+            self.glyphs = self.font.place_line(self.text, &self.bidi, line, self.width, self.size, &self.options).0.into_iter();
+        }
     }
 }
 
@@ -105,3 +279,42 @@ pub struct Glyph {
     pub scale: f32,
     pub glyph_key: GlyphKey,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_contours_are_closed() {
+        let font = Font::from_bytes(include_bytes!("../res/SourceSansPro-Regular.ttf")).unwrap();
+        let path = font.build_glyph(font.glyph_key('o').unwrap()).unwrap();
+        let commands: Vec<PathCommand> = path.commands().collect();
+        let moves = commands.iter().filter(|command| matches!(command, PathCommand::MoveTo(_))).count();
+        let closes = commands.iter().filter(|command| matches!(command, PathCommand::Close)).count();
+        assert_eq!((moves, closes), (2, 2));
+    }
+
+    /* fonts without GPOS kerning fall back to their kern table */
+    #[cfg(not(feature = "shaping"))]
+    #[test]
+    fn kern_table_fallback() {
+        let mut data = include_bytes!("../res/SourceSansPro-Regular.ttf").to_vec();
+        let font = Font::from_bytes(&data).unwrap();
+        let (a, v) = (font.font.glyph_index('A').unwrap(), font.font.glyph_index('V').unwrap());
+
+        /* the GPOS table record is taken over by a kern table with one pair, appended to the file */
+        let record = 12 + 16 * data[12..].chunks(16).position(|record| &record[..4] == b"GPOS").unwrap();
+        let kern: Vec<u8> = [0, 1, 0, 20, 1, 1, 0, 0, 0, a.0, v.0, (-77i16) as u16]
+            .iter().flat_map(|value: &u16| value.to_be_bytes().to_vec()).collect();
+        let offset = data.len() as u32;
+        data[record..record + 4].copy_from_slice(b"kern");
+        data[record + 8..record + 12].copy_from_slice(&offset.to_be_bytes());
+        data[record + 12..record + 16].copy_from_slice(&(kern.len() as u32).to_be_bytes());
+        data.extend(kern);
+
+        let font = Font::from_bytes(&data).unwrap();
+        assert!(font.kerning.is_none());
+        assert_eq!(font.kerning(a, v), -77);
+        assert_eq!(font.kerning(v, a), 0);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::geom::*;
+use crate::path::*;
+use crate::font::*;
+use crate::renderer::PATH_TEXTURE_SIZE;
+
+use std::fmt;
+use std::io;
+
+/* little-endian: magic, version, fill rule, precision, offset, size, then the encoded buffer
+followed by the source segments and contours used for hit testing and measurement */
+const MAGIC: [u8; 4] = *b"GPTH";
+/* a glyph set is its own magic, version, a fingerprint of the font data and a count,
+then each glyph id followed by its path */
+const GLYPHS_MAGIC: [u8; 4] = *b"GGLY";
+pub const PATH_FORMAT_VERSION: u16 = 1;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PathFormatError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    UnexpectedEnd,
+    Invalid,
+    FontMismatch,
+}
+
+impl fmt::Display for PathFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathFormatError::InvalidMagic => write!(f, "data is not a serialized path"),
+            PathFormatError::UnsupportedVersion(version) => write!(f, "unsupported path format version {}", version),
+            PathFormatError::UnexpectedEnd => write!(f, "unexpected end of path data"),
+            PathFormatError::Invalid => write!(f, "malformed path data"),
+            PathFormatError::FontMismatch => write!(f, "glyphs were saved from a different font"),
+        }
+    }
+}
+
+impl std::error::Error for PathFormatError {}
+
+impl Path {
+    pub fn save<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + 6 * self.buffer.len() + 24 * self.segments.len() + 9 * self.contours.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&PATH_FORMAT_VERSION.to_le_bytes());
+        bytes.push(match self.fill_rule { FillRule::NonZero => 0, FillRule::EvenOdd => 1 });
+        bytes.push(match self.precision { Precision::Standard => 0, Precision::High => 1 });
+        for &value in &[self.offset.x, self.offset.y, self.size.x, self.size.y] {
+            bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.buffer.len() as u32).to_le_bytes());
+        for texel in self.buffer.iter() {
+            for &value in texel {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&(self.segments.len() as u32).to_le_bytes());
+        for segment in self.segments.iter() {
+            for point in &[segment.p1, segment.p2, segment.p3] {
+                bytes.extend_from_slice(&point.x.to_bits().to_le_bytes());
+                bytes.extend_from_slice(&point.y.to_bits().to_le_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&(self.contours.len() as u32).to_le_bytes());
+        for contour in self.contours.iter() {
+            bytes.extend_from_slice(&(contour.start as u32).to_le_bytes());
+            bytes.extend_from_slice(&(contour.end as u32).to_le_bytes());
+            bytes.push(contour.closed as u8);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Path, PathFormatError> {
+        let mut reader = Reader { bytes };
+        let path = Path::read(&mut reader)?;
+        if !reader.bytes.is_empty() {
+            return Err(PathFormatError::Invalid);
+        }
+        Ok(path)
+    }
+
+    /* reads one path from the front of the reader, so that several can be stored back to back */
+    pub(crate) fn read(reader: &mut Reader) -> Result<Path, PathFormatError> {
+        if reader.take(4)? != MAGIC {
+            return Err(PathFormatError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if version != PATH_FORMAT_VERSION {
+            return Err(PathFormatError::UnsupportedVersion(version));
+        }
+
+        let fill_rule = match reader.u8()? {
+            0 => FillRule::NonZero,
+            1 => FillRule::EvenOdd,
+            _ => return Err(PathFormatError::Invalid),
+        };
+        let precision = match reader.u8()? {
+            0 => Precision::Standard,
+            1 => Precision::High,
+            _ => return Err(PathFormatError::Invalid),
+        };
+        let offset = Vec2::new(reader.f32()?, reader.f32()?);
+        let size = Vec2::new(reader.f32()?, reader.f32()?);
+
+        let texels = reader.count(6)?;
+        let mut buffer = Vec::with_capacity(texels);
+        for _ in 0..texels {
+            buffer.push([reader.u16()?, reader.u16()?, reader.u16()?]);
+        }
+
+        let count = reader.count(24)?;
+        let mut segments = Vec::with_capacity(count);
+        for _ in 0..count {
+            let p1 = Vec2::new(reader.f32()?, reader.f32()?);
+            let p2 = Vec2::new(reader.f32()?, reader.f32()?);
+            let p3 = Vec2::new(reader.f32()?, reader.f32()?);
+            segments.push(Segment { p1, p2, p3 });
+        }
+
+        let count = reader.count(9)?;
+        let mut contours = Vec::with_capacity(count);
+        for _ in 0..count {
+            let start = reader.u32()? as usize;
+            let end = reader.u32()? as usize;
+            let closed = reader.u8()? != 0;
+            if start >= end || end > segments.len() {
+                return Err(PathFormatError::Invalid);
+            }
+            contours.push(Contour { start, end, closed });
+        }
+
+        if !valid(&buffer, precision) {
+            return Err(PathFormatError::Invalid);
+        }
+
+        Ok(Path::from_parts(offset, size, fill_rule, precision, buffer, segments, contours))
+    }
+}
+
+impl<'a> Font<'a> {
+    /* outlines for the glyphs of the given characters, to be loaded with load_glyphs instead of being built at runtime */
+    pub fn save_glyphs<W: io::Write>(&self, chars: &str, writer: &mut W) -> io::Result<()> {
+        let mut keys: Vec<GlyphKey> = chars.chars().filter_map(|c| self.glyph_key(c)).collect();
+        keys.sort_by_key(|key| key.0);
+        keys.dedup();
+
+        let glyphs: Vec<(GlyphKey, Path)> = keys.into_iter().filter_map(|key| {
+            self.build_glyph(key).ok().map(|path| (key, path))
+        }).collect();
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&GLYPHS_MAGIC);
+        bytes.extend_from_slice(&PATH_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&fingerprint(self.data).to_le_bytes());
+        bytes.extend_from_slice(&(glyphs.len() as u32).to_le_bytes());
+        for (key, path) in glyphs.iter() {
+            bytes.extend_from_slice(&key.0.to_le_bytes());
+            bytes.extend_from_slice(&path.to_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+
+    pub fn load_glyphs(&mut self, bytes: &[u8]) -> Result<(), PathFormatError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != GLYPHS_MAGIC {
+            return Err(PathFormatError::InvalidMagic);
+        }
+        let version = reader.u16()?;
+        if version != PATH_FORMAT_VERSION {
+            return Err(PathFormatError::UnsupportedVersion(version));
+        }
+        if reader.u64()? != fingerprint(self.data) {
+            return Err(PathFormatError::FontMismatch);
+        }
+
+        let count = reader.u32()?;
+        let mut glyphs = Vec::new();
+        for _ in 0..count {
+            let key = GlyphKey(reader.u16()?);
+            glyphs.push((key, Path::read(&mut reader)?));
+        }
+        if !reader.bytes.is_empty() {
+            return Err(PathFormatError::Invalid);
+        }
+
+        self.glyphs.extend(glyphs);
+        Ok(())
+    }
+}
+
+/* checks that the header's tables fit in the buffer and that every band, cell and reference in them
+stays within its table, so the shader never reads past the path */
+fn valid(buffer: &[[u16; 3]], precision: Precision) -> bool {
+    if buffer.len() < 2 || buffer.len() > PATH_TEXTURE_SIZE {
+        return false;
+    }
+    let [fill_rule, bands, segments_offset] = buffer[0];
+    let [rows, columns, encoded_precision] = buffer[1];
+    let (bands, segments_offset) = (bands as usize, segments_offset as usize);
+    let cells = rows as usize * columns as usize;
+    let stride = match precision { Precision::Standard => 2, Precision::High => 4 };
+    let tables = 2 + bands + cells;
+    if fill_rule > 1
+        || bands == 0
+        || encoded_precision != (precision == Precision::High) as u16
+        || tables > segments_offset
+        || segments_offset > buffer.len()
+        || (buffer.len() - segments_offset) % stride != 0 {
+        return false;
+    }
+
+    let count = (buffer.len() - segments_offset) / stride;
+    let refs: Vec<u16> = buffer[tables..segments_offset].iter().flat_map(|texel| texel.iter().cloned()).collect();
+    buffer[2..2 + bands].iter().all(|band| band[0] as usize <= count)
+        && buffer[2 + bands..tables].iter().all(|&[start, length, _]| {
+            let (start, end) = (start as usize, start as usize + length as usize);
+            end <= refs.len() && refs[start..end].iter().all(|&segment| (segment as usize) < count)
+        })
+}
+
+/* 64-bit FNV-1a hash of the font file, identifying the font a glyph set was saved from */
+fn fingerprint(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, count: usize) -> Result<&'a [u8], PathFormatError> {
+        if self.bytes.len() < count {
+            return Err(PathFormatError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, PathFormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, PathFormatError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, PathFormatError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, PathFormatError> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    fn f32(&mut self) -> Result<f32, PathFormatError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    /* element count, rejected up front if the remaining data cannot hold that many elements */
+    fn count(&mut self, size: usize) -> Result<usize, PathFormatError> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(size) > self.bytes.len() {
+            return Err(PathFormatError::UnexpectedEnd);
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] = include_bytes!("../res/SourceSansPro-Regular.ttf");
+
+    fn path(precision: Precision) -> Path {
+        let mut builder = PathBuilder::new();
+        builder.precision(precision).move_to(0.0, 0.0).quadratic_to(5.0, 10.0, 10.0, 0.0).close();
+        builder.move_to(2.0, -3.0).line_to(8.0, -3.0);
+        builder.build().unwrap()
+    }
+
+    /* enough segments to be split into a grid of several cells */
+    fn grid_path() -> Path {
+        let mut builder = PathBuilder::new();
+        for i in 0..40 {
+            builder.rect(3.0 * i as f32, (i % 7) as f32, 2.0, 2.0 + (i % 5) as f32);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn path_round_trip() {
+        for path in &[path(Precision::Standard), path(Precision::High), grid_path()] {
+            let bytes = path.to_bytes();
+            let loaded = Path::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.to_bytes(), bytes);
+            assert_eq!(loaded.commands().collect::<Vec<_>>(), path.commands().collect::<Vec<_>>());
+        }
+        assert!(grid_path().buffer[1][0] * grid_path().buffer[1][1] > 1);
+    }
+
+    #[test]
+    fn truncated_path() {
+        let bytes = grid_path().to_bytes();
+        for &length in &[0, 3, 5, 27, 28, 100, bytes.len() - 1] {
+            assert_eq!(Path::from_bytes(&bytes[..length]).err(), Some(PathFormatError::UnexpectedEnd));
+        }
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut bytes = path(Precision::Standard).to_bytes();
+        bytes[0] = b'X';
+        assert_eq!(Path::from_bytes(&bytes).err(), Some(PathFormatError::InvalidMagic));
+
+        let mut bytes = path(Precision::Standard).to_bytes();
+        bytes[4..6].copy_from_slice(&7u16.to_le_bytes());
+        assert_eq!(Path::from_bytes(&bytes).err(), Some(PathFormatError::UnsupportedVersion(7)));
+
+        let mut bytes = path(Precision::Standard).to_bytes();
+        bytes.push(0);
+        assert_eq!(Path::from_bytes(&bytes).err(), Some(PathFormatError::Invalid));
+    }
+
+    #[test]
+    fn invalid_cell_range() {
+        let path = grid_path();
+        let bands = path.buffer[0][1] as usize;
+        /* the buffer starts after the 28 byte header, and its first cell after the band map */
+        let cell = 28 + 6 * (2 + bands);
+
+        let mut bytes = path.to_bytes();
+        bytes[cell + 2..cell + 4].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(Path::from_bytes(&bytes).err(), Some(PathFormatError::Invalid));
+
+        let mut bytes = path.to_bytes();
+        bytes[cell..cell + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(Path::from_bytes(&bytes).err(), Some(PathFormatError::Invalid));
+    }
+
+    #[test]
+    fn glyph_set_round_trip() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let mut bytes = Vec::new();
+        font.save_glyphs("Ag", &mut bytes).unwrap();
+
+        let mut loaded = Font::from_bytes(FONT).unwrap();
+        loaded.load_glyphs(&bytes).unwrap();
+        let key = font.glyph_key('g').unwrap();
+        assert_eq!(loaded.glyphs[&key].to_bytes(), font.build_glyph(key).unwrap().to_bytes());
+        assert_eq!(loaded.glyphs.len(), 2);
+    }
+
+    #[test]
+    fn glyph_set_font_mismatch() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let mut bytes = Vec::new();
+        font.save_glyphs("A", &mut bytes).unwrap();
+
+        let mut data = FONT.to_vec();
+        *data.last_mut().unwrap() ^= 1;
+        let mut other = Font::from_bytes(&data).unwrap();
+        assert_eq!(other.load_glyphs(&bytes).err(), Some(PathFormatError::FontMismatch));
+        assert!(other.glyphs.is_empty());
+    }
+}
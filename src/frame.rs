@@ -4,6 +4,23 @@ use crate::renderer::*;
 use crate::font::*;
 
 use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DrawError {
+    /* the paths drawn so far have filled the renderer's path texture */
+    OutOfSpace,
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrawError::OutOfSpace => write!(f, "path texture has no room for another path"),
+        }
+    }
+}
+
+impl std::error::Error for DrawError {}
 
 pub struct Frame<'c, 'r> {
     cache: &'c mut Cache,
@@ -16,6 +33,7 @@ pub struct Frame<'c, 'r> {
 
 impl<'c, 'r> Frame<'c, 'r> {
     pub fn new(cache: &'c mut Cache, renderer: &'r mut dyn Renderer, width: f32, height: f32) -> Frame<'c, 'r> {
+        cache.frame += 1;
         Frame {
             cache,
             renderer,
@@ -30,20 +48,32 @@ impl<'c, 'r> Frame<'c, 'r> {
         self.renderer.clear(color.to_linear_premul());
     }
 
-    pub fn draw_path(&mut self, path: &Path, path_key: PathKey, position: Vec2, transform: Mat2x2, color: Color) {
-        let index = if let Some(&index) = self.cache.paths.get(&path_key) {
-            index
-        } else {
-            let index = self.cache.paths_free;
-            self.cache.paths_free += path.buffer.len() as u16;
-            self.cache.paths.insert(path_key, index);
+    pub fn draw_path(&mut self, path: &Path, path_key: PathKey, transform: Transform, color: Color) -> Result<(), DrawError> {
+        let entry = self.cache.paths.get(&path_key).cloned();
+        let index = match entry {
+            Some(entry) if !entry.stale => entry.index,
+            /* invalidated paths are uploaded again in place when they still fit */
+            Some(entry) if path.buffer.len() <= entry.capacity as usize => {
+                self.cache.paths.insert(path_key, PathEntry { stale: false, ..entry });
+                self.renderer.upload(entry.index, &path.buffer);
+                entry.index
+            }
+            _ => {
+                /* removed first, so that a failed allocation does not leave the entry pointing at freed space */
+                if let Some(entry) = self.cache.paths.remove(&path_key) {
+                    self.cache.free.push((entry.index, entry.capacity));
+                }
+                let capacity = path.buffer.len() as u16;
+                let index = self.cache.allocate(capacity).ok_or(DrawError::OutOfSpace)?;
+                self.cache.paths.insert(path_key, PathEntry { index, capacity, stale: false });
 
-            self.renderer.upload(index, &path.buffer);
+                self.renderer.upload(index, &path.buffer);
 
-            index
+                index
+            }
         };
 
-        let quad = path.get_quad(position, transform);
+        let quad = path.get_quad(transform);
         let positions = [
             quad.vertices[0].pixel_to_ndc(self.width, self.height),
             quad.vertices[1].pixel_to_ndc(self.width, self.height),
@@ -61,42 +91,104 @@ impl<'c, 'r> Frame<'c, 'r> {
             Vertex { pos: [positions[3].x, positions[3].y], col, uv: [quad.uv[3].x, quad.uv[3].y], path },
         ]);
         self.indices.extend_from_slice(&[i, i + 1, i + 2, i, i + 2, i + 3]);
+        Ok(())
+    }
+
+    pub fn draw_text(&mut self, font: &Font, font_key: FontKey, size: f32, text: &str, transform: Transform, color: Color) -> Result<(), DrawError> {
+        self.draw_text_with(font, font_key, size, text, &LayoutOptions::new(), transform, color)
     }
 
-    pub fn draw_text(&mut self, font: &Font, font_key: FontKey, size: f32, text: &str, position: Vec2, transform: Mat2x2, color: Color) {
+    pub fn draw_text_with(&mut self, font: &Font, font_key: FontKey, size: f32, text: &str, options: &LayoutOptions, transform: Transform, color: Color) -> Result<(), DrawError> {
         let mut glyphs = std::mem::replace(&mut self.cache.glyphs, HashMap::new());
-        for glyph in font.layout(text, size) {
+        let mut result = Ok(());
+        for glyph in font.layout_with(text, size, options) {
             let key = (font_key, glyph.glyph_key);
             let entry = if let Some(entry) = glyphs.get(&key) {
                 entry
             } else {
                 glyphs.insert(key, GlyphEntry {
-                    path: font.build_glyph(glyph.glyph_key),
+                    /* glyphs without outlines, like spaces, are cached as such */
+                    path: font.build_glyph(glyph.glyph_key).ok(),
                     key: self.cache.add_path(),
                 });
                 glyphs.get(&key).unwrap()
             };
 
-            if entry.path.buffer.len() > 0 {
-                self.draw_path(&entry.path, entry.key, position + transform * glyph.position, glyph.scale * transform, color);
+            if let Some(ref path) = entry.path {
+                let glyph_transform = Transform::translate(glyph.position.x, glyph.position.y) * Transform::scale(glyph.scale, glyph.scale);
+                result = self.draw_path(path, entry.key, transform * glyph_transform, color);
+                if result.is_err() {
+                    break;
+                }
             }
         }
         self.cache.glyphs = glyphs;
+        result
     }
 
-    pub fn draw_rect(&mut self, position: Vec2, dimensions: Vec2, transform: Mat2x2, color: Color) {
+    pub fn draw_rect(&mut self, position: Vec2, dimensions: Vec2, transform: Transform, color: Color) -> Result<(), DrawError> {
         if self.cache.rect.is_none() {
             let path = PathBuilder::new()
                 .line_to(0.0, 1.0)
                 .line_to(1.0, 1.0)
                 .line_to(1.0, 0.0)
-                .build();
+                .build()
+                .unwrap();
             self.cache.rect = Some((self.cache.add_path(), path));
         }
 
         let (path_key, path) = self.cache.rect.take().unwrap();
-        self.draw_path(&path, path_key, position, transform * Mat2x2::new(dimensions.x, 0.0, 0.0, dimensions.y), color);
+        let result = self.draw_path(&path, path_key, transform * Transform::translate(position.x, position.y) * Transform::scale(dimensions.x, dimensions.y), color);
         self.cache.rect = Some((path_key, path));
+        result
+    }
+
+    pub fn draw_rounded_rect(&mut self, position: Vec2, dimensions: Vec2, radii: [f32; 4], transform: Transform, color: Color) -> Result<(), DrawError> {
+        let shape = Shape::RoundedRect([
+            dimensions.x.to_bits(), dimensions.y.to_bits(),
+            radii[0].to_bits(), radii[1].to_bits(), radii[2].to_bits(), radii[3].to_bits(),
+        ]);
+        self.draw_shape(shape, transform * Transform::translate(position.x, position.y), color, || {
+            PathBuilder::new().rounded_rect(0.0, 0.0, dimensions.x, dimensions.y, radii).build()
+        })
+    }
+
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, transform: Transform, color: Color) -> Result<(), DrawError> {
+        self.draw_ellipse(center, Vec2::new(radius, radius), transform, color)
+    }
+
+    pub fn draw_ellipse(&mut self, center: Vec2, radii: Vec2, transform: Transform, color: Color) -> Result<(), DrawError> {
+        self.draw_shape(Shape::Circle, transform * Transform::translate(center.x, center.y) * Transform::scale(radii.x, radii.y), color, || {
+            PathBuilder::new().circle(0.0, 0.0, 1.0).build()
+        })
+    }
+
+    pub fn draw_regular_polygon(&mut self, center: Vec2, radius: f32, sides: u32, transform: Transform, color: Color) -> Result<(), DrawError> {
+        self.draw_shape(Shape::RegularPolygon(sides), transform * Transform::translate(center.x, center.y) * Transform::scale(radius, radius), color, || {
+            PathBuilder::new().regular_polygon(0.0, 0.0, 1.0, sides).build()
+        })
+    }
+
+    pub fn draw_star(&mut self, center: Vec2, outer_radius: f32, inner_radius: f32, points: u32, transform: Transform, color: Color) -> Result<(), DrawError> {
+        let ratio = inner_radius / outer_radius;
+        self.draw_shape(Shape::Star(points, ratio.to_bits()), transform * Transform::translate(center.x, center.y) * Transform::scale(outer_radius, outer_radius), color, || {
+            PathBuilder::new().star(0.0, 0.0, 1.0, ratio, points).build()
+        })
+    }
+
+    /* shapes that fail to build, e.g. from non-finite dimensions, are skipped */
+    fn draw_shape<F: FnOnce() -> Result<Path, PathError>>(&mut self, shape: Shape, transform: Transform, color: Color, build: F) -> Result<(), DrawError> {
+        let (path_key, path) = if let Some(entry) = self.cache.shapes.remove(&shape) {
+            (entry.key, entry.path)
+        } else if let Ok(path) = build() {
+            self.cache.evict_shapes();
+            (self.cache.add_path(), path)
+        } else {
+            return Ok(());
+        };
+        let result = self.draw_path(&path, path_key, transform, color);
+        self.cache.shapes.insert(shape, ShapeEntry { key: path_key, path, frame: self.cache.frame });
+        result
     }
 
     pub fn finish(self) {
@@ -112,22 +204,46 @@ pub struct FontKey(u32);
 
 #[derive(Copy, Clone)]
 struct PathEntry {
-    indices: u16,
-    vertices: u16,
+    index: u16,
+    capacity: u16,
+    stale: bool,
 }
 
-struct GlyphEntry {
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Shape {
+    RoundedRect([u32; 6]),
+    Circle,
+    RegularPolygon(u32),
+    Star(u32, u32),
+}
+
+struct ShapeEntry {
+    key: PathKey,
     path: Path,
+    /* the last frame the shape was drawn in */
+    frame: u64,
+}
+
+/* every size of a rounded rectangle is its own path, so shapes beyond this many are evicted,
+least recently drawn first */
+const MAX_SHAPES: usize = 256;
+
+struct GlyphEntry {
+    path: Option<Path>,
     key: PathKey,
 }
 
 pub struct Cache {
-    paths: HashMap<PathKey, u16>,
+    paths: HashMap<PathKey, PathEntry>,
     next_path_key: u32,
     glyphs: HashMap<(FontKey, GlyphKey), GlyphEntry>,
     next_font_key: u32,
     rect: Option<(PathKey, Path)>,
+    shapes: HashMap<Shape, ShapeEntry>,
     paths_free: u16,
+    /* texture regions of evicted and reallocated paths, as index and capacity */
+    free: Vec<(u16, u16)>,
+    frame: u64,
 }
 
 impl Cache {
@@ -138,7 +254,10 @@ impl Cache {
             glyphs: HashMap::new(),
             next_font_key: 1,
             rect: None,
+            shapes: HashMap::new(),
             paths_free: 0,
+            free: Vec::new(),
+            frame: 0,
         }
     }
 
@@ -148,6 +267,48 @@ impl Cache {
         PathKey(path_key)
     }
 
+    /* the next draw with this key uploads its path again, for paths that change between frames */
+    pub fn invalidate_path(&mut self, path_key: PathKey) {
+        if let Some(entry) = self.paths.get_mut(&path_key) {
+            entry.stale = true;
+        }
+    }
+
+    /* first fit among freed regions, otherwise at the end of the used space if the texture has room */
+    fn allocate(&mut self, capacity: u16) -> Option<u16> {
+        if let Some(i) = self.free.iter().position(|&(_, free)| free >= capacity) {
+            let (index, free) = self.free[i];
+            if free > capacity {
+                self.free[i] = (index + capacity, free - capacity);
+            } else {
+                self.free.remove(i);
+            }
+            return Some(index);
+        }
+        let index = self.paths_free;
+        let end = index.checked_add(capacity).filter(|&end| end as usize <= PATH_TEXTURE_SIZE)?;
+        self.paths_free = end;
+        Some(index)
+    }
+
+    /* frees the least recently drawn shapes to make room for another, keeping those drawn in the current frame */
+    fn evict_shapes(&mut self) {
+        while self.shapes.len() >= MAX_SHAPES {
+            let frame = self.frame;
+            let oldest = self.shapes.iter()
+                .filter(|(_, entry)| entry.frame != frame)
+                .min_by_key(|(_, entry)| entry.frame)
+                .map(|(&shape, _)| shape);
+            let entry = match oldest {
+                Some(shape) => self.shapes.remove(&shape).unwrap(),
+                None => break,
+            };
+            if let Some(path) = self.paths.remove(&entry.key) {
+                self.free.push((path.index, path.capacity));
+            }
+        }
+    }
+
     pub fn add_font(&mut self) -> FontKey {
         let font_key = self.next_font_key;
         self.next_font_key += 1;
@@ -178,3 +339,40 @@ impl Color {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* records the end of the furthest upload */
+    struct Uploads(usize);
+
+    impl Renderer for Uploads {
+        fn clear(&mut self, _color: [f32; 4]) {}
+        fn draw(&mut self, _vertices: &[Vertex], _indices: &[u16]) {}
+        fn upload(&mut self, index: u16, paths: &[[u16; 3]]) {
+            self.0 = self.0.max(index as usize + paths.len());
+        }
+    }
+
+    #[test]
+    fn full_texture() {
+        let mut builder = PathBuilder::new();
+        for i in 0..500 {
+            builder.rect(3.0 * i as f32, (i % 7) as f32, 2.0, 2.0);
+        }
+        let path = builder.build().unwrap();
+
+        let mut cache = Cache::new();
+        let mut uploads = Uploads(0);
+        let mut frame = Frame::new(&mut cache, &mut uploads, 100.0, 100.0);
+        let mut result = Ok(());
+        for _ in 0..PATH_TEXTURE_SIZE / path.buffer.len() + 1 {
+            let path_key = frame.cache.add_path();
+            result = frame.draw_path(&path, path_key, Transform::id(), Color::rgba(0.0, 0.0, 0.0, 1.0));
+        }
+        frame.finish();
+        assert_eq!(result, Err(DrawError::OutOfSpace));
+        assert!(uploads.0 <= PATH_TEXTURE_SIZE);
+    }
+}
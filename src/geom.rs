@@ -1,6 +1,6 @@
 use std::ops;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -103,6 +103,17 @@ impl ops::SubAssign for Vec2 {
     }
 }
 
+impl ops::Neg for Vec2 {
+    type Output = Vec2;
+    #[inline]
+    fn neg(self) -> Vec2 {
+        Vec2 {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
 impl ops::Mul<f32> for Vec2 {
     type Output = Vec2;
     #[inline]
@@ -132,7 +143,35 @@ impl ops::MulAssign<f32> for Vec2 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Rect {
+    #[inline]
+    pub fn new(min: Vec2, max: Vec2) -> Rect {
+        Rect { min, max }
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    #[inline]
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x && self.min.y <= point.y && point.y <= self.max.y
+    }
+
+    #[inline]
+    pub fn union(self, other: Rect) -> Rect {
+        Rect { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Mat2x2([f32; 4]);
 
 impl Mat2x2 {
@@ -152,6 +191,23 @@ impl Mat2x2 {
     pub fn rotate(angle: f32) -> Mat2x2 {
         Mat2x2([angle.cos(), angle.sin(), -angle.sin(), angle.cos()])
     }
+
+    /* shear by the given angles along the x and y axes */
+    pub fn skew(x: f32, y: f32) -> Mat2x2 {
+        Mat2x2([1.0, x.tan(), y.tan(), 1.0])
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.0[0] * self.0[3] - self.0[1] * self.0[2]
+    }
+
+    pub fn inverse(&self) -> Option<Mat2x2> {
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some((1.0 / det) * Mat2x2([self.0[3], -self.0[1], -self.0[2], self.0[0]]))
+    }
 }
 
 impl ops::Mul<Mat2x2> for Mat2x2 {
@@ -212,3 +268,88 @@ impl ops::MulAssign<f32> for Mat2x2 {
         *self = *self * other;
     }
 }
+
+/* affine transform mapping p to matrix * p + offset */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Transform {
+    pub matrix: Mat2x2,
+    pub offset: Vec2,
+}
+
+impl Transform {
+    /* row-major linear part followed by the translation */
+    pub fn new(a: f32, b: f32, c: f32, d: f32, x: f32, y: f32) -> Transform {
+        Transform { matrix: Mat2x2::new(a, b, c, d), offset: Vec2::new(x, y) }
+    }
+
+    pub fn id() -> Transform {
+        Transform { matrix: Mat2x2::id(), offset: Vec2::new(0.0, 0.0) }
+    }
+
+    pub fn translate(x: f32, y: f32) -> Transform {
+        Transform { matrix: Mat2x2::id(), offset: Vec2::new(x, y) }
+    }
+
+    pub fn scale(x: f32, y: f32) -> Transform {
+        Mat2x2::new(x, 0.0, 0.0, y).into()
+    }
+
+    pub fn rotate(angle: f32) -> Transform {
+        Mat2x2::rotate(angle).into()
+    }
+
+    pub fn skew(x: f32, y: f32) -> Transform {
+        Mat2x2::skew(x, y).into()
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.matrix.determinant()
+    }
+
+    pub fn inverse(&self) -> Option<Transform> {
+        let matrix = self.matrix.inverse()?;
+        Some(Transform { matrix, offset: -(matrix * self.offset) })
+    }
+
+    #[inline]
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.matrix * point + self.offset
+    }
+
+    #[inline]
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        self.matrix * vector
+    }
+}
+
+impl From<Mat2x2> for Transform {
+    fn from(matrix: Mat2x2) -> Transform {
+        Transform { matrix, offset: Vec2::new(0.0, 0.0) }
+    }
+}
+
+impl ops::Mul<Transform> for Transform {
+    type Output = Transform;
+    #[inline]
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            matrix: self.matrix * rhs.matrix,
+            offset: self.matrix * rhs.offset + self.offset,
+        }
+    }
+}
+
+impl ops::MulAssign<Transform> for Transform {
+    #[inline]
+    fn mul_assign(&mut self, other: Transform) {
+        *self = *self * other;
+    }
+}
+
+impl ops::Mul<Vec2> for Transform {
+    type Output = Vec2;
+    #[inline]
+    fn mul(self, rhs: Vec2) -> Vec2 {
+        self.transform_point(rhs)
+    }
+}
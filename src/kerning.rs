@@ -0,0 +1,322 @@
+/* pair adjustment from the GPOS kern feature, read directly from the font data since
+ttf-parser only exposes the kern table */
+pub(crate) struct Kerning<'a> {
+    gpos: &'a [u8],
+    /* offsets of the pair adjustment subtables within GPOS, grouped by lookup */
+    lookups: Vec<Vec<usize>>,
+}
+
+impl<'a> Kerning<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Option<Kerning<'a>> {
+        let gpos = table(data, *b"GPOS")?;
+        let feature_list = u16_at(gpos, 6)? as usize;
+        let lookup_list = u16_at(gpos, 8)? as usize;
+
+        let mut indices = Vec::new();
+        for i in 0..u16_at(gpos, feature_list)? as usize {
+            let record = feature_list + 2 + 6 * i;
+            if gpos.get(record..record + 4)? != b"kern" {
+                continue;
+            }
+            let feature = feature_list + u16_at(gpos, record + 4)? as usize;
+            for j in 0..u16_at(gpos, feature + 2)? as usize {
+                indices.push(u16_at(gpos, feature + 4 + 2 * j)?);
+            }
+        }
+        indices.sort();
+        indices.dedup();
+
+        let mut lookups = Vec::new();
+        for index in indices {
+            let lookup = lookup_list + u16_at(gpos, lookup_list + 2 + 2 * index as usize)? as usize;
+            let kind = u16_at(gpos, lookup)?;
+            let mut subtables = Vec::new();
+            for i in 0..u16_at(gpos, lookup + 4)? as usize {
+                let subtable = lookup + u16_at(gpos, lookup + 6 + 2 * i)? as usize;
+                /* extension subtables point to a subtable of another type with a 32 bit offset */
+                match kind {
+                    2 => subtables.push(subtable),
+                    9 if u16_at(gpos, subtable + 2)? == 2 => subtables.push(subtable + u32_at(gpos, subtable + 4)? as usize),
+                    _ => {}
+                }
+            }
+            if !subtables.is_empty() {
+                lookups.push(subtables);
+            }
+        }
+
+        if lookups.is_empty() { None } else { Some(Kerning { gpos, lookups }) }
+    }
+
+    /* horizontal advance adjustment between two glyphs in font units */
+    pub(crate) fn pair(&self, left: u16, right: u16) -> Option<i16> {
+        let mut total = None;
+        for lookup in self.lookups.iter() {
+            if let Some(value) = lookup.iter().filter_map(|&subtable| self.pair_adjustment(subtable, left, right)).next() {
+                total = Some(total.unwrap_or(0i16).saturating_add(value));
+            }
+        }
+        total
+    }
+
+    fn pair_adjustment(&self, subtable: usize, left: u16, right: u16) -> Option<i16> {
+        let gpos = self.gpos;
+        let format = u16_at(gpos, subtable)?;
+        let coverage_index = coverage(gpos, subtable + u16_at(gpos, subtable + 2)? as usize, left)?;
+        let value_format1 = u16_at(gpos, subtable + 4)?;
+        let value_format2 = u16_at(gpos, subtable + 6)?;
+        let size1 = value_size(value_format1);
+        let size2 = value_size(value_format2);
+
+        match format {
+            1 => {
+                if coverage_index >= u16_at(gpos, subtable + 8)? as usize {
+                    return None;
+                }
+                let pair_set = subtable + u16_at(gpos, subtable + 10 + 2 * coverage_index)? as usize;
+                let record_size = 2 + size1 + size2;
+                let (mut low, mut high) = (0, u16_at(gpos, pair_set)? as usize);
+                while low < high {
+                    let middle = (low + high) / 2;
+                    let record = pair_set + 2 + middle * record_size;
+                    let glyph = u16_at(gpos, record)?;
+                    if glyph < right {
+                        low = middle + 1;
+                    } else if glyph > right {
+                        high = middle;
+                    } else {
+                        return x_advance(gpos, record + 2, value_format1);
+                    }
+                }
+                None
+            }
+            2 => {
+                let class1 = class(gpos, subtable + u16_at(gpos, subtable + 8)? as usize, left)? as usize;
+                let class2 = class(gpos, subtable + u16_at(gpos, subtable + 10)? as usize, right)? as usize;
+                let class1_count = u16_at(gpos, subtable + 12)? as usize;
+                let class2_count = u16_at(gpos, subtable + 14)? as usize;
+                if class1 >= class1_count || class2 >= class2_count {
+                    return None;
+                }
+                let record = subtable + 16 + (class1 * class2_count + class2) * (size1 + size2);
+                x_advance(gpos, record, value_format1)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/* the first font's table with the given tag, also inside a font collection */
+fn table(data: &[u8], tag: [u8; 4]) -> Option<&[u8]> {
+    let font = if data.get(0..4)? == b"ttcf" { u32_at(data, 12)? as usize } else { 0 };
+    for i in 0..u16_at(data, font + 4)? as usize {
+        let record = font + 12 + 16 * i;
+        if data.get(record..record + 4)? == tag {
+            let offset = u32_at(data, record + 8)? as usize;
+            let length = u32_at(data, record + 12)? as usize;
+            return data.get(offset..offset + length);
+        }
+    }
+    None
+}
+
+fn coverage(data: &[u8], offset: usize, glyph: u16) -> Option<usize> {
+    match u16_at(data, offset)? {
+        1 => {
+            for i in 0..u16_at(data, offset + 2)? as usize {
+                if u16_at(data, offset + 4 + 2 * i)? == glyph {
+                    return Some(i);
+                }
+            }
+            None
+        }
+        2 => {
+            for i in 0..u16_at(data, offset + 2)? as usize {
+                let range = offset + 4 + 6 * i;
+                let start = u16_at(data, range)?;
+                if start <= glyph && glyph <= u16_at(data, range + 2)? {
+                    return Some(u16_at(data, range + 4)? as usize + (glyph - start) as usize);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/* glyphs missing from a class definition are in class 0 */
+fn class(data: &[u8], offset: usize, glyph: u16) -> Option<u16> {
+    match u16_at(data, offset)? {
+        1 => {
+            let start = u16_at(data, offset + 2)?;
+            let count = u16_at(data, offset + 4)?;
+            if start <= glyph && glyph - start < count {
+                u16_at(data, offset + 6 + 2 * (glyph - start) as usize)
+            } else {
+                Some(0)
+            }
+        }
+        2 => {
+            for i in 0..u16_at(data, offset + 2)? as usize {
+                let range = offset + 4 + 6 * i;
+                if u16_at(data, range)? <= glyph && glyph <= u16_at(data, range + 2)? {
+                    return u16_at(data, range + 4);
+                }
+            }
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+/* a value record holds one 16 bit field for each bit set in the low byte of its format */
+fn value_size(format: u16) -> usize {
+    2 * (format & 0xFF).count_ones() as usize
+}
+
+fn x_advance(data: &[u8], record: usize, format: u16) -> Option<i16> {
+    if format & 0x4 == 0 {
+        return None;
+    }
+    u16_at(data, record + value_size(format & 0x3)).map(|value| value as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes().to_vec()).collect()
+    }
+
+    /* a font file with nothing but a GPOS table whose kern feature uses each lookup, given as its type and subtables */
+    fn font(lookups: &[(u16, Vec<Vec<u8>>)]) -> Vec<u8> {
+        let count = lookups.len() as u16;
+        let mut gpos = u16s(&[1, 0, 0, 10, 0]);
+        gpos.extend(u16s(&[1]));
+        gpos.extend_from_slice(b"kern");
+        gpos.extend(u16s(&[8, 0, count]));
+        gpos.extend(u16s(&(0..count).collect::<Vec<u16>>()));
+
+        let lookup_list = gpos.len();
+        gpos[8..10].copy_from_slice(&(lookup_list as u16).to_be_bytes());
+        gpos.extend(u16s(&[count]));
+        gpos.extend(vec![0; 2 * lookups.len()]);
+        for (i, (kind, subtables)) in lookups.iter().enumerate() {
+            let lookup = gpos.len();
+            let offset = lookup_list + 2 + 2 * i;
+            gpos[offset..offset + 2].copy_from_slice(&((lookup - lookup_list) as u16).to_be_bytes());
+            gpos.extend(u16s(&[*kind, 0, subtables.len() as u16]));
+            gpos.extend(vec![0; 2 * subtables.len()]);
+            for (j, subtable) in subtables.iter().enumerate() {
+                let offset = lookup + 6 + 2 * j;
+                let start = gpos.len();
+                gpos[offset..offset + 2].copy_from_slice(&((start - lookup) as u16).to_be_bytes());
+                gpos.extend_from_slice(subtable);
+            }
+        }
+
+        let mut data = u16s(&[1, 0, 1, 0, 0, 0]);
+        data.extend_from_slice(b"GPOS");
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&28u32.to_be_bytes());
+        data.extend_from_slice(&(gpos.len() as u32).to_be_bytes());
+        data.extend(gpos);
+        data
+    }
+
+    /* a single pair of glyphs, with the coverage table after the pair set */
+    fn format1(left: u16, right: u16, value: i16) -> Vec<u8> {
+        let mut subtable = u16s(&[1, 18, 0x4, 0, 1, 12]);
+        subtable.extend(u16s(&[1, right, value as u16]));
+        subtable.extend(u16s(&[1, 1, left]));
+        subtable
+    }
+
+    /* left glyphs in a range of class 1 and right glyphs listed as class 1, the rest in class 0 */
+    fn format2(left: (u16, u16), right: &[u16], value: i16) -> Vec<u8> {
+        let mut subtable = u16s(&[2, 24, 0x4, 0, 34, 44, 2, 2]);
+        subtable.extend(u16s(&[0, 0, 0, value as u16]));
+        subtable.extend(u16s(&[2, 1, left.0, left.1, 0]));
+        subtable.extend(u16s(&[2, 1, left.0, left.1, 1]));
+        subtable.extend(u16s(&[1, right[0], right.len() as u16]));
+        subtable.extend(u16s(&vec![1; right.len()]));
+        subtable
+    }
+
+    fn extension(subtable: Vec<u8>) -> Vec<u8> {
+        let mut extension = u16s(&[1, 2, 0, 8]);
+        extension.extend(subtable);
+        extension
+    }
+
+    #[test]
+    fn pair_format1() {
+        let data = font(&[(2, vec![format1(5, 7, -50)])]);
+        let kerning = Kerning::new(&data).unwrap();
+        assert_eq!(kerning.pair(5, 7), Some(-50));
+        assert_eq!(kerning.pair(5, 8), None);
+        assert_eq!(kerning.pair(7, 5), None);
+    }
+
+    #[test]
+    fn pair_format2() {
+        let data = font(&[(2, vec![format2((10, 12), &[20, 21], -30)])]);
+        let kerning = Kerning::new(&data).unwrap();
+        assert_eq!(kerning.pair(11, 21), Some(-30));
+        assert_eq!(kerning.pair(12, 22), Some(0));
+        assert_eq!(kerning.pair(13, 21), None);
+    }
+
+    #[test]
+    fn extension_lookups() {
+        let data = font(&[(9, vec![extension(format1(5, 7, -50))]), (2, vec![format2((5, 5), &[7], 10)])]);
+        let kerning = Kerning::new(&data).unwrap();
+        assert_eq!(kerning.pair(5, 7), Some(-40));
+
+        /* the first subtable of a lookup with a match wins */
+        let data = font(&[(9, vec![extension(format1(5, 7, -50)), extension(format1(5, 7, 20))])]);
+        assert_eq!(Kerning::new(&data).unwrap().pair(5, 7), Some(-50));
+    }
+
+    #[test]
+    fn no_kern_feature() {
+        let mut data = font(&[(2, vec![format1(5, 7, -50)])]);
+        assert!(Kerning::new(&data[..10]).is_none());
+        let feature = data.windows(4).position(|window| window == b"kern").unwrap();
+        data[feature..feature + 4].copy_from_slice(b"liga");
+        assert!(Kerning::new(&data).is_none());
+    }
+
+    #[test]
+    fn malformed_tables() {
+        let data = font(&[(9, vec![extension(format1(5, 7, -50))]), (2, vec![format2((10, 12), &[20, 21], -30)])]);
+        for length in 0..data.len() {
+            if let Some(kerning) = Kerning::new(&data[..length]) {
+                kerning.pair(5, 7);
+                kerning.pair(11, 21);
+            }
+        }
+        for i in 28..data.len() {
+            for &byte in &[0x00, 0x7F, 0xFF] {
+                let mut data = data.clone();
+                data[i] = byte;
+                if let Some(kerning) = Kerning::new(&data) {
+                    kerning.pair(5, 7);
+                    kerning.pair(11, 21);
+                    kerning.pair(u16::MAX, u16::MAX);
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,29 @@
 mod geom;
 mod path;
+mod boolean;
+mod stroke;
+mod shapes;
+mod measure;
+mod format;
+mod morph;
+mod svg;
+#[cfg(not(feature = "shaping"))]
+mod kerning;
 mod font;
+mod wrap;
+mod bidi;
 mod frame;
 mod renderer;
 pub mod renderers;
 
 pub use crate::geom::*;
 pub use crate::path::*;
+pub use crate::boolean::*;
+pub use crate::stroke::*;
+pub use crate::measure::*;
+pub use crate::format::*;
+pub use crate::svg::*;
 pub use crate::font::*;
+pub use crate::wrap::*;
 pub use crate::frame::*;
 pub use crate::renderer::*;
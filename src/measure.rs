@@ -0,0 +1,106 @@
+use crate::geom::*;
+use crate::path::*;
+
+pub struct PathMeasure {
+    contours: Vec<ContourMeasure>,
+}
+
+pub struct ContourMeasure {
+    segments: Vec<Segment>,
+    /* arc length at the end of each segment */
+    lengths: Vec<f32>,
+    closed: bool,
+}
+
+impl PathBuilder {
+    pub fn measure(&self) -> PathMeasure {
+        PathMeasure::new(&self.segments, &self.contours().collect::<Vec<_>>())
+    }
+}
+
+impl Path {
+    pub fn measure(&self) -> PathMeasure {
+        PathMeasure::new(&self.segments, &self.contours)
+    }
+}
+
+impl PathMeasure {
+    pub(crate) fn new(segments: &[Segment], contours: &[Contour]) -> PathMeasure {
+        PathMeasure {
+            contours: contours.iter().map(|contour| {
+                ContourMeasure::new(&segments[contour.start..contour.end], contour.closed)
+            }).collect(),
+        }
+    }
+
+    pub fn contours(&self) -> &[ContourMeasure] {
+        &self.contours
+    }
+
+    pub fn length(&self) -> f32 {
+        self.contours.iter().map(|contour| contour.length()).sum()
+    }
+
+    pub fn bounds(&self) -> Option<Rect> {
+        self.contours.iter().map(|contour| contour.bounds()).fold(None, |bounds, contour| {
+            Some(bounds.map_or(contour, |bounds: Rect| bounds.union(contour)))
+        })
+    }
+}
+
+impl ContourMeasure {
+    fn new(segments: &[Segment], closed: bool) -> ContourMeasure {
+        let mut lengths = Vec::with_capacity(segments.len());
+        let mut length = 0.0;
+        for segment in segments {
+            length += segment.length();
+            lengths.push(length);
+        }
+        ContourMeasure { segments: segments.to_vec(), lengths, closed }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lengths.last().cloned().unwrap_or(0.0)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /* distances wrap around on closed contours and are clamped to the ends of open ones */
+    pub fn point_at(&self, distance: f32) -> Vec2 {
+        let (segment, t) = self.locate(distance);
+        segment.point(t)
+    }
+
+    /* unit tangent in the direction of travel */
+    pub fn tangent_at(&self, distance: f32) -> Vec2 {
+        let (segment, t) = self.locate(distance);
+        let derivative = segment.derivative(t);
+        if derivative.length() > 1.0e-6 {
+            derivative.normalized()
+        } else if segment.p1.distance(segment.p3) > 0.0 {
+            (segment.p3 - segment.p1).normalized()
+        } else {
+            Vec2::new(1.0, 0.0)
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        segments_bounds(&self.segments).unwrap()
+    }
+
+    fn locate(&self, distance: f32) -> (&Segment, f32) {
+        let length = self.length();
+        let distance = if self.closed && length > 0.0 {
+            distance.rem_euclid(length)
+        } else {
+            distance.max(0.0).min(length)
+        };
+
+        let index = self.lengths.iter().position(|&end| distance <= end).unwrap_or(self.segments.len() - 1);
+        let start = if index > 0 { self.lengths[index - 1] } else { 0.0 };
+        let segment = &self.segments[index];
+        (segment, segment.t_at_length(distance - start))
+    }
+}
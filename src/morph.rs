@@ -0,0 +1,98 @@
+use crate::geom::*;
+use crate::path::*;
+
+impl Path {
+    /* paths can be interpolated when their contours have matching segment counts */
+    pub fn is_compatible(&self, other: &Path) -> bool {
+        self.contours.len() == other.contours.len() && self.contours.iter().zip(other.contours.iter()).all(|(a, b)| {
+            a.end - a.start == b.end - b.start
+        })
+    }
+
+    pub fn interpolate(&self, other: &Path, t: f32) -> Result<Path, PathError> {
+        if !self.is_compatible(other) {
+            return Err(PathError::Incompatible);
+        }
+
+        let segments: Vec<Segment> = self.segments.iter().zip(other.segments.iter()).map(|(a, b)| {
+            Segment { p1: Vec2::lerp(t, a.p1, b.p1), p2: Vec2::lerp(t, a.p2, b.p2), p3: Vec2::lerp(t, a.p3, b.p3) }
+        }).collect();
+        Path::build_with_contours(&segments, &self.contours, self.fill_rule, self.precision)
+    }
+
+    /* resamples both paths to a common structure by subdividing segments, pairing contours in order
+    and growing unmatched ones from a point, so that the results can be interpolated */
+    pub fn make_compatible(&self, other: &Path) -> Result<(Path, Path), PathError> {
+        let mut a_segments = Vec::new();
+        let mut b_segments = Vec::new();
+        let mut contours = Vec::new();
+        for i in 0..self.contours.len().max(other.contours.len()) {
+            /* open contours are matched as they are filled, closed */
+            let a = self.contours.get(i).map(|&contour| fill_segments(&self.segments, &[contour]));
+            let b = other.contours.get(i).map(|&contour| fill_segments(&other.segments, &[contour]));
+            let count = a.as_ref().map_or(0, |a| a.len()).max(b.as_ref().map_or(0, |b| b.len()));
+
+            let a = a.map_or_else(|| collapsed(b.as_ref().unwrap(), count), |a| subdivide(&a, count));
+            let mut b = b.map_or_else(|| collapsed(&a, count), |b| subdivide(&b, count));
+            if area(&a) * area(&b) < 0.0 {
+                b = reverse(&b);
+            }
+            b = align(&a, &b);
+
+            let start = a_segments.len();
+            a_segments.extend(a);
+            b_segments.extend(b);
+            contours.push(Contour { start, end: a_segments.len(), closed: true });
+        }
+
+        Ok((
+            Path::build_with_contours(&a_segments, &contours, self.fill_rule, self.precision)?,
+            Path::build_with_contours(&b_segments, &contours, other.fill_rule, other.precision)?,
+        ))
+    }
+}
+
+/* splits the longest segments in half until there are count of them */
+fn subdivide(segments: &[Segment], count: usize) -> Vec<Segment> {
+    let mut segments = segments.to_vec();
+    while segments.len() < count {
+        let mut longest = 0;
+        for i in 1..segments.len() {
+            if segments[i].length() > segments[longest].length() {
+                longest = i;
+            }
+        }
+        let (first, second) = segments[longest].split_at(0.5);
+        segments[longest] = first;
+        segments.insert(longest + 1, second);
+    }
+    segments
+}
+
+/* a contour of count segments shrunk to the center of another */
+fn collapsed(segments: &[Segment], count: usize) -> Vec<Segment> {
+    let bounds = segments_bounds(segments).unwrap();
+    let center = 0.5 * (bounds.min + bounds.max);
+    vec![Segment { p1: center, p2: center, p3: center }; count]
+}
+
+fn area(segments: &[Segment]) -> f32 {
+    0.5 * segments.iter().map(|segment| segment.p1.cross(segment.p3)).sum::<f32>()
+}
+
+/* rotates a closed contour to start at the segment that best matches the other contour point for point */
+fn align(a: &[Segment], b: &[Segment]) -> Vec<Segment> {
+    let mut best = 0;
+    let mut best_distance = std::f32::INFINITY;
+    for shift in 0..b.len() {
+        let distance: f32 = a.iter().enumerate().map(|(i, segment)| {
+            let other = b[(i + shift) % b.len()].p1;
+            (segment.p1 - other).dot(segment.p1 - other)
+        }).sum();
+        if distance < best_distance {
+            best = shift;
+            best_distance = distance;
+        }
+    }
+    b[best..].iter().chain(b[..best].iter()).cloned().collect()
+}
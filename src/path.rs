@@ -1,21 +1,74 @@
 use crate::geom::*;
 use crate::frame::*;
+use crate::renderer::PATH_TEXTURE_SIZE;
 
+use std::fmt;
+
+#[derive(Clone)]
 pub struct Path {
     pub(crate) offset: Vec2,
     pub(crate) size: Vec2,
+    pub(crate) fill_rule: FillRule,
+    pub(crate) precision: Precision,
     pub(crate) buffer: Vec<[u16; 3]>,
+    pub(crate) segments: Vec<Segment>,
+    pub(crate) contours: Vec<Contour>,
+    monotone: Vec<Segment>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PathCommand {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadraticTo(Vec2, Vec2),
+    Close,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/* coordinates are quantized relative to the path's bounding box, to 16 bits by default
+or to 32 bits at twice the texture space for large or detailed paths, of which the shader's
+f32 arithmetic resolves 24 */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Precision {
+    Standard,
+    High,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PathError {
+    NonFinite,
+    TooManySegments(usize),
+    Empty,
+    Incompatible,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathError::NonFinite => write!(f, "path contains non-finite coordinates"),
+            PathError::TooManySegments(count) => write!(f, "path has too many segments ({}) to be encoded", count),
+            PathError::Empty => write!(f, "path encloses no area"),
+            PathError::Incompatible => write!(f, "paths have different contour structures"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
 #[derive(Copy, Clone)]
 pub struct Segment {
-    p1: Vec2,
-    p2: Vec2,
-    p3: Vec2,
+    pub(crate) p1: Vec2,
+    pub(crate) p2: Vec2,
+    pub(crate) p3: Vec2,
 }
 
 impl Segment {
-    fn split_at(&self, t: f32) -> (Segment, Segment) {
+    pub(crate) fn split_at(&self, t: f32) -> (Segment, Segment) {
         let p12 = Vec2::lerp(t, self.p1, self.p2);
         let p23 = Vec2::lerp(t, self.p2, self.p3);
         let point = Vec2::lerp(t, p12, p23);
@@ -25,7 +78,135 @@ impl Segment {
         )
     }
 
-    fn split_to_monotone(&self) -> [Option<Segment>; 3] {
+    pub(crate) fn split_range(&self, t1: f32, t2: f32) -> Segment {
+        let (left, _) = self.split_at(t2);
+        if t2 > 0.0 {
+            left.split_at(t1 / t2).1
+        } else {
+            left
+        }
+    }
+
+    /* the same curve traced from its end to its start */
+    pub(crate) fn reversed(&self) -> Segment {
+        Segment { p1: self.p3, p2: self.p2, p3: self.p1 }
+    }
+
+    pub(crate) fn point(&self, t: f32) -> Vec2 {
+        Vec2::lerp(t, Vec2::lerp(t, self.p1, self.p2), Vec2::lerp(t, self.p2, self.p3))
+    }
+
+    pub(crate) fn derivative(&self, t: f32) -> Vec2 {
+        2.0 * Vec2::lerp(t, self.p2 - self.p1, self.p3 - self.p2)
+    }
+
+    /* arc length from 0 to t using 8-point Gauss-Legendre quadrature */
+    pub(crate) fn length_to(&self, t: f32) -> f32 {
+        const NODES: [(f32, f32); 4] = [
+            (0.18343464, 0.36268378),
+            (0.5255324, 0.31370664),
+            (0.7966665, 0.22238103),
+            (0.96028986, 0.10122854),
+        ];
+
+        let mut length = 0.0;
+        for &(x, w) in NODES.iter() {
+            length += w * self.derivative(0.5 * t * (1.0 - x)).length();
+            length += w * self.derivative(0.5 * t * (1.0 + x)).length();
+        }
+        0.5 * t * length
+    }
+
+    pub(crate) fn length(&self) -> f32 {
+        self.length_to(1.0)
+    }
+
+    /* inverts length_to with safeguarded Newton iterations */
+    pub(crate) fn t_at_length(&self, length: f32) -> f32 {
+        let total = self.length();
+        if length <= 0.0 || total <= 0.0 {
+            return 0.0;
+        }
+        if length >= total {
+            return 1.0;
+        }
+
+        let (mut low, mut high) = (0.0, 1.0);
+        let mut t = length / total;
+        for _ in 0..16 {
+            let error = self.length_to(t) - length;
+            if error.abs() < 1.0e-5 * total {
+                break;
+            }
+            if error > 0.0 { high = t; } else { low = t; }
+
+            let speed = self.derivative(t).length();
+            let next = t - error / speed;
+            t = if speed > 0.0 && next > low && next < high { next } else { 0.5 * (low + high) };
+        }
+        t
+    }
+
+    /* parameter at which a segment monotone in y crosses the given y coordinate */
+    pub(crate) fn solve_y(&self, y: f32) -> f32 {
+        solve(self.p1.y, self.p2.y, self.p3.y, y)
+    }
+
+    /* distance to the closest point, refining the best of a few samples with Newton iterations */
+    pub(crate) fn distance(&self, point: Vec2) -> f32 {
+        const SAMPLES: usize = 8;
+
+        let mut best = 0.0;
+        let mut best_distance = std::f32::INFINITY;
+        for i in 0..=SAMPLES {
+            let t = i as f32 / SAMPLES as f32;
+            let distance = self.point(t).distance(point);
+            if distance < best_distance {
+                best = t;
+                best_distance = distance;
+            }
+        }
+
+        let second = 2.0 * (self.p1 - 2.0 * self.p2 + self.p3);
+        let mut t = best;
+        for _ in 0..4 {
+            let offset = self.point(t) - point;
+            let derivative = self.derivative(t);
+            let f = offset.dot(derivative);
+            let df = derivative.dot(derivative) + offset.dot(second);
+            if df <= 0.0 {
+                break;
+            }
+            t = (t - f / df).max(0.0).min(1.0);
+        }
+
+        best_distance.min(self.point(t).distance(point))
+    }
+
+    /* tight bounds, including the extrema between the endpoints */
+    pub(crate) fn bounds(&self) -> Rect {
+        fn extremum(x1: f32, x2: f32, x3: f32) -> Option<f32> {
+            let t = (x1 - x2) / (x1 - 2.0 * x2 + x3);
+            if t > 0.0 && t < 1.0 { Some(t) } else { None }
+        }
+
+        let mut bounds = Rect::new(self.p1.min(self.p3), self.p1.max(self.p3));
+        for t in extremum(self.p1.x, self.p2.x, self.p3.x).into_iter().chain(extremum(self.p1.y, self.p2.y, self.p3.y)) {
+            let point = self.point(t);
+            bounds = bounds.union(Rect::new(point, point));
+        }
+        bounds
+    }
+
+    pub(crate) fn transform(&self, transform: Transform) -> Segment {
+        Segment {
+            p1: transform.transform_point(self.p1),
+            p2: transform.transform_point(self.p2),
+            p3: transform.transform_point(self.p3),
+        }
+    }
+
+    pub(crate) fn split_to_monotone(&self) -> [Option<Segment>; 3] {
         fn monotone(x1: f32, x2: f32, x3: f32) -> bool {
             (x1 <= x2 && x2 <= x3) || (x3 <= x2 && x2 <= x1)
         }
@@ -64,45 +245,222 @@ impl Segment {
     }
 }
 
+/* a run of segments traced from its end back to its start */
+pub(crate) fn reverse(segments: &[Segment]) -> Vec<Segment> {
+    segments.iter().rev().map(Segment::reversed).collect()
+}
+
+/* smallest rectangle containing the segments, if there are any */
+pub(crate) fn segments_bounds(segments: &[Segment]) -> Option<Rect> {
+    segments.iter().map(|segment| segment.bounds()).fold(None, |bounds, segment| {
+        Some(bounds.map_or(segment, |bounds: Rect| bounds.union(segment)))
+    })
+}
+
+/* parameter at which a quadratic monotone in one coordinate reaches the value v, like the shader's solve */
+pub(crate) fn solve(x1: f32, x2: f32, x3: f32, v: f32) -> f32 {
+    let a = x1 - 2.0 * x2 + x3;
+    let b = x2 - x1;
+    let c = x1 - v;
+    let q = -(b + b.signum() * (b * b - a * c).max(0.0).sqrt());
+    let ta = q / a;
+    let tb = c / q;
+    let t = if (0.0..=1.0).contains(&ta) { ta } else { tb };
+    if t.is_finite() { t.max(0.0).min(1.0) } else { 0.0 }
+}
+
+pub(crate) fn monotone(segments: &[Segment]) -> Vec<Segment> {
+    let mut segments_monotone = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let [s1, s2, s3] = segment.split_to_monotone();
+        segments_monotone.extend(s1.into_iter().chain(s2).chain(s3));
+    }
+    segments_monotone
+}
+
+/* nonzero winding number of a point with respect to monotone segments, counting crossings to its right like the shader */
+pub(crate) fn winding(segments: &[Segment], point: Vec2) -> i32 {
+    let mut winding = 0;
+    for segment in segments {
+        if (segment.p1.y <= point.y) == (segment.p3.y <= point.y) {
+            continue;
+        }
+        if segment.point(segment.solve_y(point.y)).x > point.x {
+            winding += if segment.p3.y > segment.p1.y { 1 } else { -1 };
+        }
+    }
+    winding
+}
+
+/* splits a list of segments into contours wherever consecutive segments do not meet */
+fn connected(segments: &[Segment]) -> Vec<Contour> {
+    let mut contours = Vec::new();
+    let mut start = 0;
+    for i in 1..=segments.len() {
+        if i == segments.len() || segments[i].p1 != segments[i - 1].p3 {
+            let closed = segments[i - 1].p3 == segments[start].p1;
+            contours.push(Contour { start, end: i, closed });
+            start = i;
+        }
+    }
+    contours
+}
+
+/* segments with every open contour implicitly closed, as required for filling */
+pub(crate) fn fill_segments(segments: &[Segment], contours: &[Contour]) -> Vec<Segment> {
+    let mut fill = Vec::with_capacity(segments.len() + contours.len());
+    for contour in contours {
+        fill.extend_from_slice(&segments[contour.start..contour.end]);
+        let first = segments[contour.start].p1;
+        let last = segments[contour.end - 1].p3;
+        if !contour.closed && first.distance(last) > 1.0e-6 {
+            fill.push(Segment { p1: last, p2: 0.5 * (last + first), p3: first });
+        }
+    }
+    fill
+}
+
+impl FillRule {
+    pub(crate) fn contains(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+const MAX_BANDS: usize = 256;
+/* paths with fewer monotone segments than this only use horizontal bands */
+const GRID_SEGMENTS: usize = 64;
+/* grid lines sit half a quantization step off the texel grid so that no endpoint lies exactly on one,
+must match the shader along with GRID_MARGIN */
+const GRID_OFFSET: f32 = 0.5 / 65535.0;
+/* cells also list segments within this fraction of a cell of their edges, covering the pixel footprint */
+const GRID_MARGIN: f32 = 0.25;
+
+/* indices k in 1..count of the vertical grid lines at k / count + GRID_OFFSET lying in [min, max),
+where line k is the right edge of column k - 1 */
+fn grid_lines(min: f32, max: f32, count: usize) -> std::ops::Range<usize> {
+    let line = |k: usize| k as f32 / count as f32 + GRID_OFFSET;
+    if count < 2 {
+        return 1..1;
+    }
+    let mut start = (((min - GRID_OFFSET) * count as f32).floor().max(1.0) as usize).min(count);
+    while start > 1 && line(start - 1) >= min {
+        start -= 1;
+    }
+    while start < count && line(start) < min {
+        start += 1;
+    }
+    let mut end = start;
+    while end < count && line(end) < max {
+        end += 1;
+    }
+    start..end
+}
+
 impl Path {
-    pub fn build(segments: &[Segment]) -> Path {
-        let mut segments_monotone = Vec::with_capacity(segments.len());
-        let mut last = Vec2::new(0.0, 0.0);
-        for segment in segments {
-            if segment.p1.y == segment.p2.y && segment.p2.y == segment.p3.y {
-                continue;
-            }
+    pub fn build(segments: &[Segment], fill_rule: FillRule, precision: Precision) -> Result<Path, PathError> {
+        Path::build_with_contours(segments, &connected(segments), fill_rule, precision)
+    }
 
-            let [s1, s2, s3] = segment.split_to_monotone();
-            if let Some(s1) = s1 { segments_monotone.push(s1); }
-            if let Some(s2) = s2 { segments_monotone.push(s2); }
-            if let Some(s3) = s3 { segments_monotone.push(s3); }
+    /* keeps the given contours as the path's outline, filling open ones as if they were closed */
+    pub(crate) fn build_with_contours(segments: &[Segment], contours: &[Contour], fill_rule: FillRule, precision: Precision) -> Result<Path, PathError> {
+        /* checked after splitting, which can overflow for huge coordinates */
+        let mut segments_monotone = monotone(&fill_segments(segments, contours));
+        let finite = |point: Vec2| point.x.is_finite() && point.y.is_finite();
+        if !segments_monotone.iter().all(|segment| finite(segment.p1) && finite(segment.p2) && finite(segment.p3)) {
+            return Err(PathError::NonFinite);
         }
 
+        let horizontal = |segment: &Segment| segment.p1.y == segment.p2.y && segment.p2.y == segment.p3.y;
         let mut min = Vec2::new(std::f32::INFINITY, std::f32::INFINITY);
         let mut max = Vec2::new(-std::f32::INFINITY, -std::f32::INFINITY);
-        for segment in segments_monotone.iter() {
+        for segment in segments_monotone.iter().filter(|segment| !horizontal(segment)) {
             min = min.min(segment.p1).min(segment.p2).min(segment.p3);
             max = max.max(segment.p1).max(segment.p2).max(segment.p3);
         }
-        if !min.x.is_finite() { min.x = 0.0; }
-        if !max.x.is_finite() { max.x = 0.0; }
-        if !min.y.is_finite() { min.y = 0.0; }
-        if !max.y.is_finite() { max.y = 0.0; }
-
         let offset = min;
         let size = max - min;
+        /* also when every segment is horizontal, leaving the bounds inverted */
+        if !(size.x > 0.0 && size.y > 0.0) {
+            return Err(PathError::Empty);
+        }
+
+        /* roughly four segments per cell, in cells that are about square */
+        let count = segments_monotone.iter().filter(|segment| !horizontal(segment)).count();
+        let aspect = (size.y / size.x).max(0.25).min(4.0);
+        let (rows, columns) = if count >= GRID_SEGMENTS {
+            let cells = 0.25 * count as f32;
+            (
+                ((cells * aspect).sqrt().ceil() as usize).max(1).min(MAX_BANDS),
+                ((cells / aspect).sqrt().ceil() as usize).max(1).min(MAX_BANDS),
+            )
+        } else {
+            (0, 0)
+        };
+        let (rows, columns) = if columns > 1 { (rows, columns) } else { (0, 0) };
+
+        let scale = match precision {
+            Precision::Standard => std::u16::MAX as f64,
+            Precision::High => std::u32::MAX as f64,
+        };
+        let convert = |x: f32, offset: f32, size: f32| -> u32 {
+            (scale * ((x as f64 - offset as f64) / size as f64)).round() as u32
+        };
+        let quantize = |segment: &Segment| [
+            convert(segment.p1.x, offset.x, size.x),
+            convert(segment.p1.y, offset.y, size.y),
+            convert(segment.p2.x, offset.x, size.x),
+            convert(segment.p2.y, offset.y, size.y),
+            convert(segment.p3.x, offset.x, size.x),
+            convert(segment.p3.y, offset.y, size.y),
+        ];
+
+        /* the grid works with the quantized segments exactly as the shader sees them */
+        let normalize = |q: &[u32; 6]| {
+            /* same operations as the shader's decoding */
+            let coordinate = |x: u32| match precision {
+                Precision::Standard => x as f32 / std::u16::MAX as f32,
+                Precision::High => x as f32 / std::u32::MAX as f32,
+            };
+            let point = |x: u32, y: u32| Vec2::new(coordinate(x), coordinate(y));
+            Segment { p1: point(q[0], q[1]), p2: point(q[2], q[3]), p3: point(q[4], q[5]) }
+        };
 
-        segments_monotone.sort_unstable_by(|segment1, segment2| {
+        /* horizontal segments never cross a row, so they are only kept where they cross the right edge of a cell,
+        which the shader needs to carry the winding number from the cell's corner down to the pixel */
+        let mut encoded: Vec<(Segment, [u32; 6], Segment)> = segments_monotone.iter().filter_map(|segment| {
+            let q = quantize(segment);
+            let normalized = normalize(&q);
+            let (x_min, x_max) = (normalized.p1.x.min(normalized.p3.x), normalized.p1.x.max(normalized.p3.x));
+            if horizontal(segment) && grid_lines(x_min, x_max, columns).is_empty() {
+                None
+            } else {
+                Some((*segment, q, normalized))
+            }
+        }).collect();
+
+        encoded.sort_unstable_by(|(_, _, segment1), (_, _, segment2)| {
             let y1 = segment1.p1.y.min(segment1.p3.y);
             let y2 = segment2.p1.y.min(segment2.p3.y);
-            y1.partial_cmp(&y2).unwrap_or(std::cmp::Ordering::Less)
+            y1.partial_cmp(&y2).unwrap()
         });
+        segments_monotone = encoded.iter().map(|&(segment, _, _)| segment).collect();
 
-        let mut map = vec![segments_monotone.len(); 16];
+        let stride = match precision { Precision::Standard => 2, Precision::High => 4 };
+        /* a path has to fit in the renderer's path texture on its own */
+        if stride * segments_monotone.len() > PATH_TEXTURE_SIZE {
+            return Err(PathError::TooManySegments(segments_monotone.len()));
+        }
+
+        /* more bands for paths with many segments, and for tall ones since bands are horizontal */
+        let bands = ((2.0 * aspect * segments_monotone.len() as f32).sqrt().ceil() as usize).max(1).min(MAX_BANDS);
+
+        let mut map = vec![segments_monotone.len(); bands];
         for (i, segment) in segments_monotone.iter().enumerate() {
             let max_y = segment.p1.y.max(segment.p3.y);
-            let max_i = ((((max_y - offset.y) / size.y) * 16.0).ceil() as usize).min(15);
+            let max_i = ((((max_y - offset.y) / size.y) * bands as f32).ceil() as usize).min(bands - 1);
             if let Some(entry) = map.get_mut(max_i) {
                 *entry = i.min(*entry);
             }
@@ -113,43 +471,202 @@ impl Path {
             *entry = min;
         }
 
-        let mut buffer = Vec::with_capacity(segments_monotone.len() + 16);
+        /* each cell lists the segments within a margin of it, or for horizontal ones those crossing its right edge;
+        the ranges of cells are counted first so that paths over the texel budget are rejected early */
+        let cell_range = |min: f32, max: f32, count: usize| {
+            let first = ((min * count as f32 - 1.0 - GRID_MARGIN).ceil().max(0.0) as usize).min(count);
+            let last = ((max * count as f32 + GRID_MARGIN).floor().max(-1.0) + 1.0) as usize;
+            first..last.min(count).max(first)
+        };
+        let ranges: Vec<_> = encoded.iter().map(|&(segment, _, normalized)| {
+            let (x_min, x_max) = (normalized.p1.x.min(normalized.p3.x), normalized.p1.x.max(normalized.p3.x));
+            let (y_min, y_max) = (normalized.p1.y.min(normalized.p3.y), normalized.p1.y.max(normalized.p3.y));
+            let columns = if horizontal(&segment) {
+                let lines = grid_lines(x_min, x_max, columns);
+                lines.start - 1..lines.end - 1
+            } else {
+                cell_range(x_min, x_max, columns)
+            };
+            (cell_range(y_min, y_max, rows), columns)
+        }).collect();
+        let count: usize = ranges.iter().map(|(rows, columns)| rows.len() * columns.len()).sum();
+
+        let tables = 2 + bands + rows * columns + count.div_ceil(3);
+        let segments_offset = (tables + 1) & !1;
+        if segments_offset + stride * segments_monotone.len() > PATH_TEXTURE_SIZE || count > std::u16::MAX as usize {
+            return Err(PathError::TooManySegments(segments_monotone.len()));
+        }
+
+        let mut cell_refs = vec![Vec::new(); rows * columns];
+        for (i, (rows, columns_range)) in ranges.into_iter().enumerate() {
+            for row in rows {
+                for column in columns_range.clone() {
+                    cell_refs[row * columns + column].push(i as u16);
+                }
+            }
+        }
+
+        /* winding number at the top right corner of each cell, found by sweeping down the cell's right edge,
+        where every segment crossing it from left to right adds one and from right to left subtracts one */
+        let mut deltas = vec![0i32; rows * columns];
+        for &(_, _, normalized) in encoded.iter() {
+            let (x_min, x_max) = (normalized.p1.x.min(normalized.p3.x), normalized.p1.x.max(normalized.p3.x));
+            let direction = if normalized.p3.x > normalized.p1.x { 1 } else { -1 };
+            for line in grid_lines(x_min, x_max, columns) {
+                let x = line as f32 / columns as f32 + GRID_OFFSET;
+                let y = normalized.point(solve(normalized.p1.x, normalized.p2.x, normalized.p3.x, x)).y;
+                /* the first row whose corner lies below the crossing */
+                let corner = |row: usize| row as f32 / rows as f32 + GRID_OFFSET;
+                let mut row = ((((y - GRID_OFFSET) * rows as f32).floor() + 1.0).max(0.0) as usize).min(rows);
+                while row > 0 && corner(row - 1) > y {
+                    row -= 1;
+                }
+                while row < rows && corner(row) <= y {
+                    row += 1;
+                }
+                if row < rows {
+                    deltas[row * columns + line - 1] += direction;
+                }
+            }
+        }
+
+        let mut cells = Vec::with_capacity(rows * columns);
+        let mut refs: Vec<u16> = Vec::with_capacity(count);
+        let mut windings = vec![0i32; columns];
+        for row in 0..rows {
+            for column in 0..columns {
+                windings[column] += deltas[row * columns + column];
+                let start = refs.len();
+                refs.extend_from_slice(&cell_refs[row * columns + column]);
+                cells.push([start as u16, (refs.len() - start) as u16, (32768 - windings[column]) as u16]);
+            }
+        }
+
+        let mut buffer = Vec::with_capacity(segments_offset + stride * segments_monotone.len());
+
+        /* header, padded to two texels to keep segments aligned */
+        buffer.push([fill_rule as u16, bands as u16, segments_offset as u16]);
+        buffer.push([rows as u16, columns as u16, precision as u16]);
 
         for i in map {
             buffer.push([i as u16, 0, 0]);
         }
-
-        #[inline(always)]
-        fn convert(x: f32, offset: f32, size: f32) -> u16 {
-            (std::u16::MAX as f32 * ((x - offset) / size)).round() as u16
+        buffer.extend_from_slice(&cells);
+        for chunk in refs.chunks(3) {
+            buffer.push([chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)]);
+        }
+        if tables < segments_offset {
+            buffer.push([0, 0, 0]);
         }
 
-        for segment in segments_monotone {
-            buffer.push([
-                convert(segment.p1.x, offset.x, size.x),
-                convert(segment.p1.y, offset.y, size.y),
-                convert(segment.p2.x, offset.x, size.x),
-            ]);
-            buffer.push([
-                convert(segment.p2.y, offset.y, size.y),
-                convert(segment.p3.x, offset.x, size.x),
-                convert(segment.p3.y, offset.y, size.y),
-            ]);
+        for &(_, q, _) in encoded.iter() {
+            match precision {
+                Precision::Standard => {
+                    buffer.push([q[0] as u16, q[1] as u16, q[2] as u16]);
+                    buffer.push([q[3] as u16, q[4] as u16, q[5] as u16]);
+                }
+                Precision::High => {
+                    /* high and low halves of each coordinate */
+                    let high = |i: usize| (q[i] >> 16) as u16;
+                    let low = |i: usize| q[i] as u16;
+                    buffer.push([high(0), low(0), high(1)]);
+                    buffer.push([low(1), high(2), low(2)]);
+                    buffer.push([high(3), low(3), high(4)]);
+                    buffer.push([low(4), high(5), low(5)]);
+                }
+            }
         }
 
-        Path {
+        Ok(Path {
             offset,
             size,
+            fill_rule,
+            precision,
             buffer,
+            segments: segments.to_vec(),
+            contours: contours.to_vec(),
+            monotone: segments_monotone,
+        })
+    }
+
+    /* reassembles a path from an already encoded buffer and its source outline */
+    pub(crate) fn from_parts(offset: Vec2, size: Vec2, fill_rule: FillRule, precision: Precision, buffer: Vec<[u16; 3]>, segments: Vec<Segment>, contours: Vec<Contour>) -> Path {
+        let monotone = monotone(&fill_segments(&segments, &contours));
+        Path { offset, size, fill_rule, precision, buffer, segments, contours, monotone }
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    pub fn bounds(&self) -> Rect {
+        segments_bounds(&self.segments).unwrap_or(Rect::new(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)))
+    }
+
+    /* the outline as it was built, where open contours are only closed implicitly for filling */
+    pub fn commands(&self) -> impl Iterator<Item = PathCommand> + '_ {
+        self.contours.iter().flat_map(move |contour| {
+            let segments = &self.segments[contour.start..contour.end];
+            std::iter::once(PathCommand::MoveTo(segments[0].p1))
+                .chain(segments.iter().map(|segment| {
+                    if segment.p2 == 0.5 * (segment.p1 + segment.p3) {
+                        PathCommand::LineTo(segment.p3)
+                    } else {
+                        PathCommand::QuadraticTo(segment.p2, segment.p3)
+                    }
+                }))
+                .chain(if contour.closed { Some(PathCommand::Close) } else { None })
+        })
+    }
+
+    pub fn to_builder(&self) -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        builder.fill_rule(self.fill_rule).precision(self.precision);
+        for command in self.commands() {
+            match command {
+                PathCommand::MoveTo(point) => { builder.move_to(point.x, point.y); }
+                PathCommand::LineTo(point) => { builder.line_to(point.x, point.y); }
+                PathCommand::QuadraticTo(control, point) => { builder.quadratic_to(control.x, control.y, point.x, point.y); }
+                PathCommand::Close => { builder.close(); }
+            }
+        }
+        builder
+    }
+
+    /* point in path coordinates, using the same winding rule as the shader */
+    pub fn contains(&self, point: Vec2) -> bool {
+        if point.x < self.offset.x || point.y < self.offset.y || point.x > self.offset.x + self.size.x || point.y > self.offset.y + self.size.y {
+            return false;
+        }
+        self.fill_rule.contains(winding(&self.monotone, point))
+    }
+
+    /* distance in path coordinates from the point to the nearest point on the outline */
+    pub fn distance(&self, point: Vec2) -> f32 {
+        self.segments.iter().fold(std::f32::INFINITY, |min, segment| min.min(segment.distance(point)))
+    }
+
+    /* tests a point in the space the path is drawn into with the given transform,
+    also accepting points within tolerance of the outline in that space */
+    pub fn hit_test(&self, transform: Transform, point: Vec2, tolerance: f32) -> bool {
+        if let Some(inverse) = transform.inverse() {
+            if self.contains(inverse.transform_point(point)) {
+                return true;
+            }
         }
+        tolerance > 0.0 && self.segments.iter().any(|segment| segment.transform(transform).distance(point) <= tolerance)
     }
 }
 
 impl Path {
-    pub fn get_quad(&self, position: Vec2, transform: Mat2x2) -> Quad {
-        let p = position + transform * Vec2::new(self.offset.x, self.offset.y);
-        let v1 = transform * Vec2::new(self.size.x, 0.0);
-        let v2 = transform * Vec2::new(0.0, self.size.y);
+    pub fn get_quad(&self, transform: Transform) -> Quad {
+        let p = transform.transform_point(self.offset);
+        let v1 = transform.transform_vector(Vec2::new(self.size.x, 0.0));
+        let v2 = transform.transform_vector(Vec2::new(0.0, self.size.y));
         let n1 = v1.normalized();
         let n2 = v2.normalized();
 
@@ -182,23 +699,59 @@ pub struct Quad {
     pub uv: [Vec2; 4],
 }
 
+#[derive(Copy, Clone)]
+pub(crate) struct Contour {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) closed: bool,
+}
+
 pub struct PathBuilder {
-    segments: Vec<Segment>,
+    pub(crate) segments: Vec<Segment>,
+    contours: Vec<Contour>,
+    contour_start: usize,
+    started: bool,
     first: Vec2,
     last: Vec2,
+    pub(crate) tolerance: f32,
+    pub(crate) fill_rule: FillRule,
+    pub(crate) precision: Precision,
 }
 
 impl PathBuilder {
     pub fn new() -> PathBuilder {
         PathBuilder {
             segments: Vec::new(),
+            contours: Vec::new(),
+            contour_start: 0,
+            started: false,
             first: Vec2::new(0.0, 0.0),
             last: Vec2::new(0.0, 0.0),
+            tolerance: 0.1,
+            fill_rule: FillRule::NonZero,
+            precision: Precision::Standard,
         }
     }
 
+    pub fn fill_rule(&mut self, fill_rule: FillRule) -> &mut Self {
+        self.fill_rule = fill_rule;
+        self
+    }
+
+    pub fn precision(&mut self, precision: Precision) -> &mut Self {
+        self.precision = precision;
+        self
+    }
+
+    /* maximum distance between a cubic and its quadratic approximation */
+    pub fn tolerance(&mut self, tolerance: f32) -> &mut Self {
+        self.tolerance = tolerance;
+        self
+    }
+
     pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
-        self.close();
+        self.finish(false);
+        self.started = true;
         self.first = Vec2::new(x, y);
         self.last = Vec2::new(x, y);
         self
@@ -225,60 +778,255 @@ impl PathBuilder {
         self
     }
 
-    pub fn arc_to(&mut self, radius: f32, large_arc: bool, winding: bool, x: f32, y: f32) -> &mut Self {
-        const MAX_ANGLE: f32 = std::f32::consts::PI / 4.0;
+    pub fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x3: f32, y3: f32) -> &mut Self {
+        let p0 = self.last;
+        let p1 = Vec2::new(x1, y1);
+        let p2 = Vec2::new(x2, y2);
+        let p3 = Vec2::new(x3, y3);
 
-        let end = Vec2::new(x, y);
-        let to_midpoint = 0.5 * (end - self.last);
-        let to_midpoint_len = to_midpoint.length();
-        let radius = radius.max(to_midpoint_len);
-        let to_center_len = (radius * radius - to_midpoint_len * to_midpoint_len).sqrt();
-        let center_dir = if large_arc == winding { -1.0 } else { 1.0 };
-        let to_center = if to_midpoint.length() == 0.0 {
-            Vec2::new(-1.0, 0.0)
-        } else {
-            Vec2::new(-to_midpoint.y, to_midpoint.x).normalized()
+        let point = |t: f32| {
+            let s = 1.0 - t;
+            (s * s * s) * p0 + (3.0 * s * s * t) * p1 + (3.0 * s * t * t) * p2 + (t * t * t) * p3
         };
-        let center = self.last + to_midpoint + center_dir * to_center_len * to_center;
-
-        let start_vector = self.last - center;
-        let start_angle = start_vector.y.atan2(start_vector.x);
-        let end_vector = end - center;
-        let end_angle = {
-            let end_angle = end_vector.y.atan2(end_vector.x);
-            if winding && end_angle < start_angle {
-                end_angle + 2.0 * std::f32::consts::PI
-            } else if !winding && end_angle > start_angle {
-                end_angle - 2.0 * std::f32::consts::PI
-            } else {
-                end_angle
-            }
+        let derivative = |t: f32| {
+            let s = 1.0 - t;
+            (3.0 * s * s) * (p1 - p0) + (6.0 * s * t) * (p2 - p1) + (3.0 * t * t) * (p3 - p2)
         };
 
-        let num_segments = (((start_angle - end_angle).abs() / MAX_ANGLE).ceil() as usize).max(1).min(8);
+        /* approximation error shrinks with the cube of the number of pieces */
+        let error = (3.0f32.sqrt() / 36.0) * (p3 - 3.0 * p2 + 3.0 * p1 - p0).length();
+        let num_segments = ((error / self.tolerance.max(1.0e-6)).cbrt().ceil() as usize).max(1).min(64);
+
         for i in 0..num_segments {
-            let angle = start_angle + ((i + 1) as f32 / num_segments as f32) * (end_angle - start_angle);
-            let normal = Vec2::new(angle.cos(), angle.sin());
-            let point = center + radius * normal;
+            let t0 = i as f32 / num_segments as f32;
+            let t1 = (i + 1) as f32 / num_segments as f32;
+            let dt = (t1 - t0) / 3.0;
 
-            let tangent = Vec2::new(-normal.y, normal.x);
-            let control = point + 0.5 * ((self.last - point).length() / tangent.dot((self.last - point).normalized())) * tangent;
+            let q0 = point(t0);
+            let q3 = if i + 1 == num_segments { p3 } else { point(t1) };
+            let q1 = q0 + dt * derivative(t0);
+            let q2 = q3 - dt * derivative(t1);
+            let control = 0.25 * (3.0 * (q1 + q2) - q0 - q3);
 
-            self.quadratic_to(control.x, control.y, point.x, point.y);
+            self.quadratic_to(control.x, control.y, q3.x, q3.y);
+        }
+
+        self
+    }
+
+    pub fn arc_to(&mut self, radius: f32, large_arc: bool, winding: bool, x: f32, y: f32) -> &mut Self {
+        self.elliptical_arc_to(radius, radius, 0.0, large_arc, winding, x, y)
+    }
+
+    /* follows the SVG arc parametrization, with the x axis rotation given in radians */
+    pub fn elliptical_arc_to(&mut self, rx: f32, ry: f32, rotation: f32, large_arc: bool, sweep: bool, x: f32, y: f32) -> &mut Self {
+        let start = self.last;
+        let end = Vec2::new(x, y);
+        if start.distance(end) == 0.0 {
+            return self;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(x, y);
         }
 
+        let rotate = Mat2x2::new(rotation.cos(), -rotation.sin(), rotation.sin(), rotation.cos());
+        let unrotate = Mat2x2::new(rotation.cos(), rotation.sin(), -rotation.sin(), rotation.cos());
+        let p = unrotate * (0.5 * (start - end));
+
+        /* scale up radii that are too small to span the endpoints */
+        let lambda = (p.x * p.x) / (rx * rx) + (p.y * p.y) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * p.y * p.y - ry * ry * p.x * p.x;
+        let denominator = rx * rx * p.y * p.y + ry * ry * p.x * p.x;
+        let sign = if large_arc != sweep { 1.0 } else { -1.0 };
+        let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+        let center_rotated = coefficient * Vec2::new(rx * p.y / ry, -ry * p.x / rx);
+        let center = rotate * center_rotated + 0.5 * (start + end);
+
+        let angle = |v: Vec2| v.y.atan2(v.x);
+        let start_angle = angle(Vec2::new((p.x - center_rotated.x) / rx, (p.y - center_rotated.y) / ry));
+        let end_angle = angle(Vec2::new((-p.x - center_rotated.x) / rx, (-p.y - center_rotated.y) / ry));
+        let mut sweep_angle = end_angle - start_angle;
+        if sweep && sweep_angle < 0.0 {
+            sweep_angle += 2.0 * std::f32::consts::PI;
+        } else if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= 2.0 * std::f32::consts::PI;
+        }
+
+        self.ellipse_segments(center, rx, ry, rotate, start_angle, sweep_angle);
+        if let Some(segment) = self.segments.last_mut() {
+            segment.p3 = end;
+        }
+        self.last = end;
         self
     }
 
-    fn close(&mut self) {
+    /* circular arc around a center, connected to the current point unless no contour has been started */
+    pub fn arc(&mut self, cx: f32, cy: f32, radius: f32, start_angle: f32, sweep_angle: f32) -> &mut Self {
+        let sweep_angle = sweep_angle.max(-2.0 * std::f32::consts::PI).min(2.0 * std::f32::consts::PI);
+        let start = Vec2::new(cx + radius * start_angle.cos(), cy + radius * start_angle.sin());
+        if self.started || self.segments.len() > self.contour_start {
+            if start.distance(self.last) > 1.0e-6 {
+                self.line_to(start.x, start.y);
+            }
+        } else {
+            self.move_to(start.x, start.y);
+        }
+
+        self.ellipse_segments(Vec2::new(cx, cy), radius, radius, Mat2x2::id(), start_angle, sweep_angle);
+        self
+    }
+
+    /* approximates the arc with one quadratic per 45 degrees, mapping unit circle arcs through the ellipse's transform */
+    pub(crate) fn ellipse_segments(&mut self, center: Vec2, rx: f32, ry: f32, rotate: Mat2x2, start_angle: f32, sweep_angle: f32) {
+        const MAX_ANGLE: f32 = std::f32::consts::PI / 4.0;
+
+        let map = |angle: f32, scale: f32| center + rotate * Vec2::new(scale * rx * angle.cos(), scale * ry * angle.sin());
+        let num_segments = ((sweep_angle.abs() / MAX_ANGLE).ceil() as usize).max(1);
+        let step = sweep_angle / num_segments as f32;
+        for i in 0..num_segments {
+            let control = map(start_angle + (i as f32 + 0.5) * step, 1.0 / (0.5 * step).cos());
+            let point = map(start_angle + (i + 1) as f32 * step, 1.0);
+            self.quadratic_to(control.x, control.y, point.x, point.y);
+        }
+    }
+
+    pub fn close(&mut self) -> &mut Self {
         if self.first.distance(self.last) > 1.0e-6 {
             self.line_to(self.first.x, self.first.y);
+        } else if let Some(segment) = self.segments[self.contour_start..].last_mut() {
+            /* snapped shut, since even a tiny gap lets winding numbers leak out along the start's scanline */
+            let line = segment.p2 == 0.5 * (segment.p1 + segment.p3);
+            segment.p3 = self.first;
+            if line {
+                segment.p2 = 0.5 * (segment.p1 + segment.p3);
+            }
+        }
+        self.finish(true);
+        self.started = false;
+        self.last = self.first;
+        self
+    }
+
+    fn finish(&mut self, closed: bool) {
+        if self.segments.len() > self.contour_start {
+            self.contours.push(Contour { start: self.contour_start, end: self.segments.len(), closed });
         }
+        self.contour_start = self.segments.len();
+    }
+
+    pub(crate) fn contours(&self) -> impl Iterator<Item = Contour> + '_ {
+        let pending = if self.segments.len() > self.contour_start {
+            Some(Contour { start: self.contour_start, end: self.segments.len(), closed: false })
+        } else {
+            None
+        };
+        self.contours.iter().cloned().chain(pending)
     }
 
-    pub fn build(&mut self) -> Path {
-        self.close();
+    pub(crate) fn fill_segments(&self) -> Vec<Segment> {
+        fill_segments(&self.segments, &self.contours().collect::<Vec<_>>())
+    }
 
-        Path::build(&self.segments)
+    pub fn build(&mut self) -> Result<Path, PathError> {
+        Path::build_with_contours(&self.segments, &self.contours().collect::<Vec<_>>(), self.fill_rule, self.precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_snaps_to_start() {
+        let mut builder = PathBuilder::new();
+        /* a full turn ends a rounding error short of its start */
+        builder.circle(0.0, 0.0, 1.0);
+        assert_eq!(builder.segments.last().unwrap().p3, builder.segments[0].p1);
+
+        let path = builder.build().unwrap();
+        for &x in &[-1.5, -1.05, 1.05, 1.5] {
+            assert!(!path.contains(Vec2::new(x, 0.0)));
+        }
+        for &x in &[-0.95, 0.0, 0.95] {
+            assert!(path.contains(Vec2::new(x, 0.0)));
+        }
+    }
+
+    /* overlapping shapes with enough segments for a grid of several cells */
+    fn shapes(precision: Precision) -> Path {
+        let mut builder = PathBuilder::new();
+        builder.precision(precision);
+        for i in 0..12 {
+            let (x, y) = ((i % 4) as f32 * 15.0, (i / 4) as f32 * 20.0);
+            builder.circle(x, y, 12.0);
+            builder.move_to(x - 5.0, y + 8.0).line_to(x + 30.0, y - 3.0).line_to(x + 2.0, y - 9.0).close();
+        }
+        builder.build().unwrap()
+    }
+
+    /* cells as start, count and stored seed, the reference table, and the segments as the shader decodes them */
+    fn decode(path: &Path) -> (usize, usize, Vec<[u16; 3]>, Vec<u16>, Vec<Segment>) {
+        let [_, bands, segments_offset] = path.buffer[0];
+        let [rows, columns, _] = path.buffer[1];
+        let (rows, columns) = (rows as usize, columns as usize);
+        let cells_start = 2 + bands as usize;
+        let cells = path.buffer[cells_start..cells_start + rows * columns].to_vec();
+        let refs = path.buffer[cells_start + rows * columns..segments_offset as usize].iter().flat_map(|texel| texel.to_vec()).collect();
+
+        let segments = match path.precision {
+            Precision::Standard => path.buffer[segments_offset as usize..].chunks(2).map(|texels| {
+                let point = |x: u16, y: u16| Vec2::new(x as f32 / 65535.0, y as f32 / 65535.0);
+                Segment { p1: point(texels[0][0], texels[0][1]), p2: point(texels[0][2], texels[1][0]), p3: point(texels[1][1], texels[1][2]) }
+            }).collect(),
+            Precision::High => path.buffer[segments_offset as usize..].chunks(4).map(|texels| {
+                let values: Vec<u16> = texels.iter().flat_map(|texel| texel.to_vec()).collect();
+                let coordinate = |i: usize| ((values[2 * i] as u32) << 16 | values[2 * i + 1] as u32) as f32 / 4294967295.0;
+                let point = |i: usize| Vec2::new(coordinate(i), coordinate(i + 1));
+                Segment { p1: point(0), p2: point(2), p3: point(4) }
+            }).collect(),
+        };
+        (rows, columns, cells, refs, segments)
+    }
+
+    #[test]
+    fn seeds_match_winding() {
+        for &precision in &[Precision::Standard, Precision::High] {
+            let path = shapes(precision);
+            let (rows, columns, cells, _, segments) = decode(&path);
+            assert!(rows > 1 && columns > 1);
+            for row in 0..rows {
+                for column in 0..columns {
+                    let corner = Vec2::new((column + 1) as f32 / columns as f32 + GRID_OFFSET, row as f32 / rows as f32 + GRID_OFFSET);
+                    let seed = 32768 - cells[row * columns + column][2] as i32;
+                    assert_eq!(seed, winding(&segments, corner), "cell {} {}", row, column);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cells_cover_segments() {
+        for &precision in &[Precision::Standard, Precision::High] {
+            let path = shapes(precision);
+            let (rows, columns, cells, refs, segments) = decode(&path);
+            for (i, segment) in segments.iter().enumerate() {
+                if segment.p1.y == segment.p3.y {
+                    continue;
+                }
+                for k in 0..=64 {
+                    let point = segment.point(k as f32 / 64.0);
+                    let row = ((point.y * rows as f32) as usize).min(rows - 1);
+                    let column = ((point.x * columns as f32) as usize).min(columns - 1);
+                    let [start, count, _] = cells[row * columns + column];
+                    assert!(refs[start as usize..(start + count) as usize].contains(&(i as u16)), "segment {} in cell {} {}", i, row, column);
+                }
+            }
+        }
     }
 }
@@ -1,3 +1,6 @@
+/* texels in the path texture of a renderer, which all paths drawn in a frame share */
+pub const PATH_TEXTURE_SIZE: usize = 16384;
+
 pub trait Renderer {
     fn clear(&mut self, color: [f32; 4]);
     fn draw(&mut self, vertices: &[Vertex], indices: &[u16]);
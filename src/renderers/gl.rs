@@ -18,7 +18,7 @@ impl GlRenderer {
             &CString::new(include_bytes!("../shader/vert.glsl") as &[u8]).unwrap(),
             &CString::new(include_bytes!("../shader/frag.glsl") as &[u8]).unwrap()).unwrap();
 
-        let paths = Texture::new(16384, 1, None);
+        let paths = Texture::new(PATH_TEXTURE_SIZE as u32, 1, None);
 
         unsafe {
             gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
@@ -185,8 +185,8 @@ trait Texel {
 }
 
 impl Texel for [u16; 3] {
-    const INTERNAL_FORMAT: GLint = gl::RGB16 as GLint;
-    const FORMAT: GLenum = gl::RGB;
+    const INTERNAL_FORMAT: GLint = gl::RGB16UI as GLint;
+    const FORMAT: GLenum = gl::RGB_INTEGER;
     const TYPE: GLenum = gl::UNSIGNED_SHORT;
 }
 
@@ -1,6 +1,10 @@
 #version 330
 
-uniform sampler2D paths;
+/* must match GRID_OFFSET and GRID_MARGIN in path.rs */
+const float GRID_OFFSET = 0.5 / 65535.0;
+const float GRID_MARGIN = 0.25;
+
+uniform usampler2D paths;
 
 in vec4 v_col;
 in vec2 v_uv;
@@ -8,49 +12,144 @@ flat in uvec2 v_path;
 
 out vec4 f_col;
 
+uvec3 fetch(uint i) {
+    return texelFetch(paths, ivec2(int(i), 0), 0).xyz;
+}
+
+/* reconstructs a high precision coordinate from its halves, which only keeps 24 significant bits as a float */
+float combine(uint high, uint low) {
+    return float((high << 16u) | low) / 4294967295.0;
+}
+
+void load(uint segments, uint index, bool precise, out vec2 p1, out vec2 p2, out vec2 p3) {
+    if (precise) {
+        uvec3 t1 = fetch(segments + 4u * index);
+        uvec3 t2 = fetch(segments + 4u * index + 1u);
+        uvec3 t3 = fetch(segments + 4u * index + 2u);
+        uvec3 t4 = fetch(segments + 4u * index + 3u);
+        p1 = vec2(combine(t1.x, t1.y), combine(t1.z, t2.x));
+        p2 = vec2(combine(t2.y, t2.z), combine(t3.x, t3.y));
+        p3 = vec2(combine(t3.z, t4.x), combine(t4.y, t4.z));
+    } else {
+        vec3 t1 = vec3(fetch(segments + 2u * index)) / 65535.0;
+        vec3 t2 = vec3(fetch(segments + 2u * index + 1u)) / 65535.0;
+        p1 = t1.xy;
+        p2 = vec2(t1.z, t2.x);
+        p3 = t2.yz;
+    }
+}
+
+/* parameter at which a quadratic monotone in this coordinate reaches the value v */
+float solve(float x1, float x2, float x3, float v) {
+    float a = x1 - 2.0 * x2 + x3;
+    float b = x2 - x1;
+    float c = x1 - v;
+    float q = -(b + (b < 0.0 ? -1.0 : 1.0) * sqrt(max(b * b - a * c, 0.0)));
+    float ta = q / a;
+    float tb = c / q;
+    return (0.0 <= ta && ta <= 1.0) ? ta : tb;
+}
+
+/* x position and antialiased horizontal coverage of a segment crossing the pixel's row */
+vec2 crossing(vec2 p1, vec2 p2, vec2 p3, float y, vec2 footprint) {
+    float t = solve(p1.y, p2.y, p3.y, y);
+    float x = mix(mix(p1.x, p2.x, t), mix(p2.x, p3.x, t), t);
+
+    vec2 tangent = mix(p2 - p1, p3 - p2, t);
+    float f = ((x - v_uv.x) * abs(tangent.y)) / length(footprint * tangent.yx);
+    return vec2(x, clamp(0.5 + f, 0.0, 1.0));
+}
+
 void main() {
     vec2 ddx = dFdx(v_uv);
     vec2 ddy = dFdy(v_uv);
     vec2 footprint = sqrt(ddx * ddx + ddy * ddy);
     vec2 y_footprint = v_uv.y + vec2(-0.5 * footprint.y, 0.5 * footprint.y);
 
-    uint start = 2u * uint(65536.0 * texelFetch(paths, ivec2(int(v_path.x + clamp(uint(y_footprint.x * 16.0), 0, 15)), 0), 0).x);
+    uvec3 header = fetch(v_path.x);
+    uvec3 grid = fetch(v_path.x + 1u);
+    uint fill_rule = header.x;
+    uint bands = header.y;
+    uint segments = v_path.x + header.z;
+    uint rows = grid.x;
+    uint columns = grid.y;
+    bool precise = grid.z == 1u;
 
     float alpha = 0.0;
-    vec3 t1 = texelFetch(paths, ivec2(int(v_path.x + 16u + start), 0), 0).xyz;
-    vec3 t2 = texelFetch(paths, ivec2(int(v_path.x + 17u + start), 0), 0).xyz;
-    for (uint i = v_path.x + 16u + start; i < v_path.y; i += 2u) {
-        vec2 p1 = t1.xy;
-        vec2 p2 = vec2(t1.z, t2.x);
-        vec2 p3 = t2.yz;
-
-        vec2 y_window = clamp(vec2(p3.y, p1.y), y_footprint.x, y_footprint.y);
-        float y_overlap = (y_window.y - y_window.x) / footprint.y;
-
-        if (min(p1.y, p3.y) > y_footprint.y) { break; }
-
-        t1 = texelFetch(paths, ivec2(int(i + 2u), 0), 0).xyz;
-        t2 = texelFetch(paths, ivec2(int(i + 3u), 0), 0).xyz;
-
-        if (y_overlap != 0.0 && max(p1.x, p3.x) > v_uv.x - 0.5 * footprint.x) {
-            float a = p1.y - 2.0 * p2.y + p3.y;
-            float b = p2.y - p1.y;
-            float c = p1.y - 0.5 * (y_window.x + y_window.y);
-            float q = -(b + (b < 0.0 ? -1.0 : 1.0) * sqrt(max(b * b - a * c, 0.0)));
-            float ta = q / a;
-            float tb = c / q;
-            float t = (0.0 <= ta && ta <= 1.0) ? ta : tb;
-            float x = mix(mix(p1.x, p2.x, t), mix(p2.x, p3.x, t), t);
-
-            vec2 tangent = mix(p2 - p1, p3 - p2, t);
-            float f = ((x - v_uv.x) * abs(tangent.y)) / length(footprint * tangent.yx);
-            float x_overlap = clamp(0.5 + f, 0.0, 1.0);
-
-            alpha += x_overlap * y_overlap;
+    if (columns > 0u && footprint.x * float(columns) < 2.0 * GRID_MARGIN && footprint.y * float(rows) < 2.0 * GRID_MARGIN) {
+        /* winding at the cell's top right corner, plus crossings on the way from there to the pixel */
+        uint column = uint(clamp(v_uv.x * float(columns), 0.0, float(columns - 1u)));
+        uint row = uint(clamp(v_uv.y * float(rows), 0.0, float(rows - 1u)));
+        float right = float(column + 1u) / float(columns) + GRID_OFFSET;
+        float top = float(row) / float(rows) + GRID_OFFSET;
+
+        uint cells = v_path.x + 2u + bands;
+        uint refs = cells + rows * columns;
+        uvec3 cell = fetch(cells + row * columns + column);
+        uint start = cell.x;
+        uint end = start + cell.y;
+        alpha = float(cell.z) - 32768.0;
+
+        for (uint i = start; i < end; i++) {
+            vec2 p1, p2, p3;
+            load(segments, fetch(refs + i / 3u)[i % 3u], precise, p1, p2, p3);
+
+            if (min(p1.y, p3.y) > y_footprint.y) { break; }
+            if (max(p1.x, p3.x) < v_uv.x - 0.5 * footprint.x) { continue; }
+
+            /* where the segment crosses the cell's right edge, if it does */
+            bool crosses = min(p1.x, p3.x) <= right && right < max(p1.x, p3.x);
+            float y_right = 0.0;
+            if (crosses) {
+                float t = solve(p1.x, p2.x, p3.x, right);
+                y_right = mix(mix(p1.y, p2.y, t), mix(p2.y, p3.y, t), t);
+                float below = clamp((y_footprint.y - y_right) / footprint.y, 0.0, 1.0) - (y_right <= top ? 1.0 : 0.0);
+                alpha += sign(p1.x - p3.x) * below;
+            }
+
+            vec2 y_window = clamp(vec2(p3.y, p1.y), y_footprint.x, y_footprint.y);
+            float y_overlap = (y_window.y - y_window.x) / footprint.y;
+            if (y_overlap != 0.0) {
+                vec2 x = crossing(p1, p2, p3, 0.5 * (y_window.x + y_window.y), footprint);
+
+                /* the part of the row beyond the right edge is already counted by the seed,
+                measured exactly so that it cancels the crossing term above */
+                float beyond = x.x > right ? y_overlap : 0.0;
+                if (crosses) {
+                    float low = min(y_window.x, y_window.y);
+                    float high = max(y_window.x, y_window.y);
+                    bool increasing = (p3.x - p1.x) * (p3.y - p1.y) > 0.0;
+                    float extent = increasing ? max(high - max(low, y_right), 0.0) : max(min(high, y_right) - low, 0.0);
+                    beyond = sign(p1.y - p3.y) * extent / footprint.y;
+                }
+
+                alpha += x.y * y_overlap - beyond;
+            }
+        }
+    } else {
+        uint band = uint(clamp(y_footprint.x * float(bands), 0.0, float(bands - 1u)));
+        uint start = fetch(v_path.x + 2u + band).x;
+        uint count = (v_path.y - segments) / (precise ? 4u : 2u);
+
+        for (uint i = start; i < count; i++) {
+            vec2 p1, p2, p3;
+            load(segments, i, precise, p1, p2, p3);
+
+            if (min(p1.y, p3.y) > y_footprint.y) { break; }
+
+            vec2 y_window = clamp(vec2(p3.y, p1.y), y_footprint.x, y_footprint.y);
+            float y_overlap = (y_window.y - y_window.x) / footprint.y;
+            if (y_overlap != 0.0 && max(p1.x, p3.x) > v_uv.x - 0.5 * footprint.x) {
+                alpha += crossing(p1, p2, p3, 0.5 * (y_window.x + y_window.y), footprint).y * y_overlap;
+            }
         }
     }
 
     float brightness = (v_col.r + v_col.g + v_col.b) / (3.0 * v_col.a);
-    alpha = clamp(abs(alpha), 0.0, 1.0);
+    alpha = abs(alpha);
+    if (fill_rule == 1u) {
+        alpha = 1.0 - abs(1.0 - mod(alpha, 2.0));
+    }
+    alpha = clamp(alpha, 0.0, 1.0);
     f_col = mix(1.0 - (1.0 - alpha) * (1.0 - alpha), alpha * alpha, sqrt(brightness)) * v_col;
 }
@@ -0,0 +1,92 @@
+use crate::geom::*;
+use crate::path::*;
+
+use std::f32::consts::PI;
+
+impl PathBuilder {
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) -> &mut Self {
+        self.move_to(x, y)
+            .line_to(x + width, y)
+            .line_to(x + width, y + height)
+            .line_to(x, y + height)
+            .close()
+    }
+
+    /* radii are given clockwise starting from the top left corner and scaled down uniformly if they overlap */
+    pub fn rounded_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radii: [f32; 4]) -> &mut Self {
+        /* negative radii are square corners, and must not count towards the overlap */
+        let [top_left, top_right, bottom_right, bottom_left] = [radii[0].max(0.0), radii[1].max(0.0), radii[2].max(0.0), radii[3].max(0.0)];
+        let scale = [
+            width / (top_left + top_right),
+            height / (top_right + bottom_right),
+            width / (bottom_right + bottom_left),
+            height / (bottom_left + top_left),
+        ].iter().filter(|scale| scale.is_finite()).fold(1.0f32, |min, &scale| min.min(scale));
+        let [top_left, top_right, bottom_right, bottom_left] = [scale * top_left, scale * top_right, scale * bottom_right, scale * bottom_left];
+
+        self.move_to(x + top_left, y);
+        self.line_to(x + width - top_right, y);
+        self.corner(x + width - top_right, y + top_right, top_right, -0.5 * PI);
+        self.line_to(x + width, y + height - bottom_right);
+        self.corner(x + width - bottom_right, y + height - bottom_right, bottom_right, 0.0);
+        self.line_to(x + bottom_left, y + height);
+        self.corner(x + bottom_left, y + height - bottom_left, bottom_left, 0.5 * PI);
+        self.line_to(x, y + top_left);
+        self.corner(x + top_left, y + top_left, top_left, PI);
+        self.close()
+    }
+
+    fn corner(&mut self, cx: f32, cy: f32, radius: f32, start_angle: f32) {
+        if radius > 0.0 {
+            self.ellipse_segments(Vec2::new(cx, cy), radius, radius, Mat2x2::id(), start_angle, 0.5 * PI);
+        }
+    }
+
+    pub fn circle(&mut self, cx: f32, cy: f32, radius: f32) -> &mut Self {
+        self.ellipse(cx, cy, radius, radius)
+    }
+
+    pub fn ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) -> &mut Self {
+        self.move_to(cx + rx, cy);
+        self.ellipse_segments(Vec2::new(cx, cy), rx, ry, Mat2x2::id(), 0.0, 2.0 * PI);
+        self.close()
+    }
+
+    /* the first vertex points up */
+    pub fn regular_polygon(&mut self, cx: f32, cy: f32, radius: f32, sides: u32) -> &mut Self {
+        let sides = sides.max(3);
+        for i in 0..sides {
+            let angle = 2.0 * PI * i as f32 / sides as f32 - 0.5 * PI;
+            let (x, y) = (cx + radius * angle.cos(), cy + radius * angle.sin());
+            if i == 0 { self.move_to(x, y); } else { self.line_to(x, y); }
+        }
+        self.close()
+    }
+
+    pub fn star(&mut self, cx: f32, cy: f32, outer_radius: f32, inner_radius: f32, points: u32) -> &mut Self {
+        let points = points.max(2);
+        for i in 0..2 * points {
+            let angle = PI * i as f32 / points as f32 - 0.5 * PI;
+            let radius = if i % 2 == 0 { outer_radius } else { inner_radius };
+            let (x, y) = (cx + radius * angle.cos(), cy + radius * angle.sin());
+            if i == 0 { self.move_to(x, y); } else { self.line_to(x, y); }
+        }
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounded_rect_negative_radius() {
+        let mut builder = PathBuilder::new();
+        builder.rounded_rect(0.0, 0.0, 10.0, 10.0, [-10.0, 5.0, 5.0, 5.0]);
+        let path = builder.build().unwrap();
+        let bounds = path.bounds();
+        assert!(bounds.min.distance(Vec2::new(0.0, 0.0)) < 1.0e-4 && bounds.max.distance(Vec2::new(10.0, 10.0)) < 1.0e-4);
+        assert!(path.contains(Vec2::new(0.5, 0.5)));
+        assert!(!path.contains(Vec2::new(9.5, 0.5)));
+    }
+}
@@ -0,0 +1,551 @@
+use crate::geom::*;
+use crate::path::*;
+use crate::boolean::*;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Debug)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    pub dashes: Vec<f32>,
+    pub dash_offset: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> StrokeStyle {
+        StrokeStyle {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl PathBuilder {
+    pub fn stroke(&self, style: &StrokeStyle) -> Result<Path, PathError> {
+        self.stroke_outline(style).build()
+    }
+
+    /* grows the filled area by the given distance, or shrinks it for negative distances,
+    by combining it with a stroke of twice that width around every contour */
+    pub fn offset(&self, distance: f32, join: LineJoin) -> PathBuilder {
+        let mut closed = PathBuilder::new();
+        closed.tolerance(self.tolerance).fill_rule(self.fill_rule).precision(self.precision);
+        for contour in self.contours() {
+            let segments = &self.segments[contour.start..contour.end];
+            closed.move_to(segments[0].p1.x, segments[0].p1.y);
+            for segment in segments {
+                closed.quadratic_to(segment.p2.x, segment.p2.y, segment.p3.x, segment.p3.y);
+            }
+            closed.close();
+        }
+
+        if distance == 0.0 || !distance.is_finite() {
+            return closed;
+        }
+
+        let mut style = StrokeStyle::new(2.0 * distance.abs());
+        style.join = join;
+        let outline = closed.stroke_outline(&style);
+        let op = if distance > 0.0 { BooleanOp::Union } else { BooleanOp::Difference };
+        let mut result = closed.boolean(&outline, op);
+        result.fill_rule(FillRule::NonZero);
+        result
+    }
+
+    pub(crate) fn stroke_outline(&self, style: &StrokeStyle) -> PathBuilder {
+        let mut stroker = Stroker {
+            out: PathBuilder::new(),
+            style,
+            distance: 0.5 * style.width,
+            tolerance: self.tolerance,
+        };
+        stroker.out.tolerance(self.tolerance).precision(self.precision);
+
+        let dashes: Vec<f32> = if style.dashes.len() % 2 == 1 {
+            style.dashes.iter().chain(style.dashes.iter()).cloned().collect()
+        } else {
+            style.dashes.clone()
+        };
+        let dashed = dashes.iter().all(|&dash| dash >= 0.0) && dashes.iter().sum::<f32>() > 0.0;
+
+        for contour in self.contours() {
+            let segments = &self.segments[contour.start..contour.end];
+            if dashed {
+                for dash in dash(segments, contour.closed, &dashes, style.dash_offset) {
+                    stroker.contour(&dash, false);
+                }
+            } else {
+                stroker.contour(segments, contour.closed);
+            }
+        }
+
+        stroker.out
+    }
+}
+
+struct Stroker<'s> {
+    out: PathBuilder,
+    style: &'s StrokeStyle,
+    distance: f32,
+    tolerance: f32,
+}
+
+impl<'s> Stroker<'s> {
+    fn contour(&mut self, segments: &[Segment], closed: bool) {
+        let filtered: Vec<Segment> = segments.iter().filter(|segment| !is_degenerate(segment)).cloned().collect();
+        if filtered.is_empty() {
+            self.dot(segments[0].p1);
+        } else if closed {
+            self.closed(&filtered);
+        } else {
+            self.open(&filtered);
+        }
+    }
+
+    fn open(&mut self, segments: &[Segment]) {
+        let reversed = reverse(segments);
+
+        let start = segments[0].p1;
+        let start_normal = normal(start_tangent(&segments[0]));
+        self.out.move_to(start.x + self.distance * start_normal.x, start.y + self.distance * start_normal.y);
+        self.side(segments, false);
+        self.cap(reversed[0].p1, -start_tangent(&reversed[0]));
+        self.side(&reversed, false);
+        self.cap(start, -start_tangent(&segments[0]));
+        self.out.close();
+    }
+
+    fn closed(&mut self, segments: &[Segment]) {
+        for side in &[segments.to_vec(), reverse(segments)] {
+            let start = side[0].p1;
+            let start_normal = normal(start_tangent(&side[0]));
+            self.out.move_to(start.x + self.distance * start_normal.x, start.y + self.distance * start_normal.y);
+            self.side(side, true);
+            self.out.close();
+        }
+    }
+
+    /* emits the left offset of a run of segments, joining consecutive segments */
+    fn side(&mut self, segments: &[Segment], closed: bool) {
+        for (i, segment) in segments.iter().enumerate() {
+            let mut pieces = Vec::new();
+            offset_segment(segment, self.distance, self.tolerance, 0, &mut pieces);
+            for piece in pieces {
+                self.out.quadratic_to(piece.p2.x, piece.p2.y, piece.p3.x, piece.p3.y);
+            }
+
+            let next = if i + 1 < segments.len() {
+                Some(&segments[i + 1])
+            } else if closed {
+                Some(&segments[0])
+            } else {
+                None
+            };
+            if let Some(next) = next {
+                self.join(segment.p3, end_tangent(segment), start_tangent(next));
+            }
+        }
+    }
+
+    fn join(&mut self, pivot: Vec2, tangent_in: Vec2, tangent_out: Vec2) {
+        let normal_in = normal(tangent_in);
+        let normal_out = normal(tangent_out);
+        let to = pivot + self.distance * normal_out;
+
+        let cross = tangent_in.cross(tangent_out);
+        let dot = tangent_in.dot(tangent_out);
+        if cross.abs() < 1.0e-6 && dot > 0.0 {
+            self.out.line_to(to.x, to.y);
+            return;
+        }
+
+        if cross > 0.0 {
+            /* inner side of the turn */
+            self.out.line_to(pivot.x, pivot.y);
+            self.out.line_to(to.x, to.y);
+            return;
+        }
+
+        match self.style.join {
+            LineJoin::Miter => {
+                let ratio = 2.0 / (normal_in + normal_out).length().max(1.0e-6);
+                if ratio <= self.style.miter_limit {
+                    let miter = pivot + (self.distance / (1.0 + normal_in.dot(normal_out))) * (normal_in + normal_out);
+                    self.out.line_to(miter.x, miter.y);
+                }
+                self.out.line_to(to.x, to.y);
+            }
+            LineJoin::Round => {
+                let angle = normal_in.cross(normal_out).atan2(normal_in.dot(normal_out));
+                self.arc(pivot, normal_in, -angle.abs());
+            }
+            LineJoin::Bevel => {
+                self.out.line_to(to.x, to.y);
+            }
+        }
+    }
+
+    fn cap(&mut self, point: Vec2, tangent: Vec2) {
+        let normal = normal(tangent);
+        let to = point - self.distance * normal;
+        match self.style.cap {
+            LineCap::Butt => {
+                self.out.line_to(to.x, to.y);
+            }
+            LineCap::Round => {
+                self.arc(point, normal, -std::f32::consts::PI);
+            }
+            LineCap::Square => {
+                let corner1 = point + self.distance * (normal + tangent);
+                let corner2 = point + self.distance * (tangent - normal);
+                self.out.line_to(corner1.x, corner1.y);
+                self.out.line_to(corner2.x, corner2.y);
+                self.out.line_to(to.x, to.y);
+            }
+        }
+    }
+
+    /* zero-length contours only produce geometry for round and square caps */
+    fn dot(&mut self, point: Vec2) {
+        let d = self.distance;
+        match self.style.cap {
+            LineCap::Butt => {}
+            LineCap::Round => {
+                self.out.move_to(point.x + d, point.y);
+                self.arc(point, Vec2::new(1.0, 0.0), 2.0 * std::f32::consts::PI);
+                self.out.close();
+            }
+            LineCap::Square => {
+                self.out.move_to(point.x - d, point.y - d);
+                self.out.line_to(point.x + d, point.y - d);
+                self.out.line_to(point.x + d, point.y + d);
+                self.out.line_to(point.x - d, point.y + d);
+                self.out.close();
+            }
+        }
+    }
+
+    /* circular arc around center starting at direction from, assuming the current point is already on it */
+    fn arc(&mut self, center: Vec2, from: Vec2, sweep: f32) {
+        self.out.ellipse_segments(center, self.distance, self.distance, Mat2x2::id(), from.y.atan2(from.x), sweep);
+    }
+}
+
+/* splits a contour into the pieces covered by the "on" intervals of a dash pattern */
+fn dash(segments: &[Segment], closed: bool, dashes: &[f32], offset: f32) -> Vec<Vec<Segment>> {
+    let total: f32 = dashes.iter().sum();
+    let mut index = 0;
+    let mut remaining = offset.rem_euclid(total);
+    while remaining > 0.0 && remaining >= dashes[index] {
+        remaining -= dashes[index];
+        index = (index + 1) % dashes.len();
+    }
+    remaining = dashes[index] - remaining;
+    let starts_on = index % 2 == 0;
+
+    let mut pieces = Vec::new();
+    let mut current = Vec::new();
+    for segment in segments {
+        let length = segment.length();
+        let epsilon = 1.0e-5 * length;
+        let mut position = 0.0;
+        loop {
+            let on = index % 2 == 0;
+            if remaining <= length - position + epsilon {
+                let end = position + remaining;
+                if on {
+                    current.push(segment.split_range(segment.t_at_length(position), segment.t_at_length(end)));
+                    pieces.push(std::mem::take(&mut current));
+                }
+                position = end;
+                index = (index + 1) % dashes.len();
+                remaining = dashes[index];
+            } else {
+                if on && length > position {
+                    current.push(segment.split_range(segment.t_at_length(position), 1.0));
+                }
+                remaining -= length - position;
+                break;
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        /* a dash running over the start of a closed contour continues into the first one */
+        if closed && starts_on && !pieces.is_empty() {
+            current.extend(pieces.remove(0));
+        }
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+fn is_degenerate(segment: &Segment) -> bool {
+    segment.p1.distance(segment.p2) + segment.p2.distance(segment.p3) < 1.0e-6
+}
+
+fn start_tangent(segment: &Segment) -> Vec2 {
+    if segment.p1.distance(segment.p2) > 1.0e-6 {
+        (segment.p2 - segment.p1).normalized()
+    } else {
+        (segment.p3 - segment.p1).normalized()
+    }
+}
+
+fn end_tangent(segment: &Segment) -> Vec2 {
+    if segment.p2.distance(segment.p3) > 1.0e-6 {
+        (segment.p3 - segment.p2).normalized()
+    } else {
+        (segment.p3 - segment.p1).normalized()
+    }
+}
+
+fn normal(tangent: Vec2) -> Vec2 {
+    Vec2::new(-tangent.y, tangent.x)
+}
+
+/* approximates the offset curve of a quadratic with quadratics, subdividing until within tolerance */
+fn offset_segment(segment: &Segment, distance: f32, tolerance: f32, depth: u32, out: &mut Vec<Segment>) {
+    const MAX_DEPTH: u32 = 8;
+
+    let tangent1 = start_tangent(segment);
+    let tangent2 = end_tangent(segment);
+    let p1 = segment.p1 + distance * normal(tangent1);
+    let p3 = segment.p3 + distance * normal(tangent2);
+
+    let denom = tangent1.cross(tangent2);
+    let p2 = if denom.abs() < 1.0e-6 {
+        0.5 * (p1 + p3)
+    } else {
+        p1 + ((p3 - p1).cross(tangent2) / denom) * tangent1
+    };
+    let offset = Segment { p1, p2, p3 };
+
+    if depth < MAX_DEPTH {
+        let (left, right) = segment.split_at(0.5);
+        let tangent_mid = start_tangent(&right);
+        let expected = right.p1 + distance * normal(tangent_mid);
+        let actual = offset.split_at(0.5).1.p1;
+        if tangent1.dot(tangent2) < 0.9 || expected.distance(actual) > tolerance {
+            offset_segment(&left, distance, tolerance, depth + 1, out);
+            offset_segment(&right, distance, tolerance, depth + 1, out);
+            return;
+        }
+    }
+
+    out.push(offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(width: f32, join: LineJoin, cap: LineCap) -> StrokeStyle {
+        StrokeStyle { join, cap, ..StrokeStyle::new(width) }
+    }
+
+    /* a right angle turning clockwise at (10, 0) */
+    fn corner() -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        builder.move_to(0.0, 0.0).line_to(10.0, 0.0).line_to(10.0, 10.0);
+        builder
+    }
+
+    fn near(a: Vec2, b: Vec2) -> bool {
+        a.distance(b) < 1.0e-3
+    }
+
+    #[test]
+    fn joins() {
+        let miter = corner().stroke(&style(2.0, LineJoin::Miter, LineCap::Butt)).unwrap();
+        let bevel = corner().stroke(&style(2.0, LineJoin::Bevel, LineCap::Butt)).unwrap();
+        let round = corner().stroke(&style(2.0, LineJoin::Round, LineCap::Butt)).unwrap();
+        for path in &[&miter, &bevel, &round] {
+            assert!(near(path.bounds().min, Vec2::new(0.0, -1.0)) && near(path.bounds().max, Vec2::new(11.0, 10.0)));
+            assert!(path.contains(Vec2::new(5.0, 0.5)) && path.contains(Vec2::new(9.5, 5.0)));
+            assert!(!path.contains(Vec2::new(5.0, 1.5)));
+        }
+
+        assert!(miter.contains(Vec2::new(10.9, -0.9)));
+        assert!(!bevel.contains(Vec2::new(10.6, -0.6)) && bevel.contains(Vec2::new(10.4, -0.4)));
+        assert!(!round.contains(Vec2::new(10.9, -0.9)) && round.contains(Vec2::new(10.6, -0.6)));
+    }
+
+    #[test]
+    fn miter_limit() {
+        /* turning back at a small angle, so the miter would reach far past the corner */
+        let mut builder = PathBuilder::new();
+        builder.move_to(0.0, 0.0).line_to(10.0, 0.0).line_to(0.0, 1.0);
+
+        let limited = builder.stroke(&style(2.0, LineJoin::Miter, LineCap::Butt)).unwrap();
+        assert!(limited.bounds().max.x < 11.5);
+        let bevel = builder.stroke(&style(2.0, LineJoin::Bevel, LineCap::Butt)).unwrap();
+        assert!(near(limited.bounds().max, bevel.bounds().max));
+
+        let unlimited = builder.stroke(&StrokeStyle { miter_limit: 1000.0, ..StrokeStyle::new(2.0) }).unwrap();
+        assert!(unlimited.bounds().max.x > 15.0);
+        assert!(unlimited.contains(Vec2::new(14.0, 0.0)));
+    }
+
+    #[test]
+    fn caps() {
+        let butt = corner().stroke(&style(2.0, LineJoin::Miter, LineCap::Butt)).unwrap();
+        let square = corner().stroke(&style(2.0, LineJoin::Miter, LineCap::Square)).unwrap();
+        let round = corner().stroke(&style(2.0, LineJoin::Miter, LineCap::Round)).unwrap();
+
+        assert!(near(butt.bounds().min, Vec2::new(0.0, -1.0)) && near(butt.bounds().max, Vec2::new(11.0, 10.0)));
+        assert!(!butt.contains(Vec2::new(-0.5, 0.0)) && !butt.contains(Vec2::new(10.0, 10.5)));
+
+        assert!(near(square.bounds().min, Vec2::new(-1.0, -1.0)) && near(square.bounds().max, Vec2::new(11.0, 11.0)));
+        assert!(square.contains(Vec2::new(-0.9, 0.9)) && square.contains(Vec2::new(9.1, 10.9)));
+
+        assert!(near(round.bounds().min, Vec2::new(-1.0, -1.0)) && near(round.bounds().max, Vec2::new(11.0, 11.0)));
+        assert!(round.contains(Vec2::new(-0.5, 0.0)) && round.contains(Vec2::new(10.0, 10.5)));
+        assert!(!round.contains(Vec2::new(-0.9, 0.9)) && !round.contains(Vec2::new(9.1, 10.9)));
+    }
+
+    #[test]
+    fn dots() {
+        let mut builder = PathBuilder::new();
+        builder.move_to(5.0, 5.0).line_to(5.0, 5.0);
+
+        assert_eq!(builder.stroke(&style(2.0, LineJoin::Miter, LineCap::Butt)).err(), Some(PathError::Empty));
+
+        let square = builder.stroke(&style(2.0, LineJoin::Miter, LineCap::Square)).unwrap();
+        assert!(near(square.bounds().min, Vec2::new(4.0, 4.0)) && near(square.bounds().max, Vec2::new(6.0, 6.0)));
+        assert!(square.contains(Vec2::new(5.9, 5.9)));
+
+        let round = builder.stroke(&style(2.0, LineJoin::Miter, LineCap::Round)).unwrap();
+        assert!(near(round.bounds().min, Vec2::new(4.0, 4.0)) && near(round.bounds().max, Vec2::new(6.0, 6.0)));
+        assert!(round.contains(Vec2::new(5.0, 5.0)) && round.contains(Vec2::new(5.9, 5.0)));
+        assert!(!round.contains(Vec2::new(5.9, 5.9)) && !round.contains(Vec2::new(6.5, 5.0)));
+    }
+
+    fn line() -> PathBuilder {
+        let mut builder = PathBuilder::new();
+        builder.move_to(0.0, 0.0).line_to(10.0, 0.0);
+        builder
+    }
+
+    /* start and end of each dash along the x axis */
+    fn extents(dashes: &[Vec<Segment>]) -> Vec<(f32, f32)> {
+        dashes.iter().map(|dash| (dash[0].p1.x, dash[dash.len() - 1].p3.x)).collect()
+    }
+
+    fn same(a: &[(f32, f32)], b: &[(f32, f32)]) -> bool {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| (a.0 - b.0).abs() < 1.0e-4 && (a.1 - b.1).abs() < 1.0e-4)
+    }
+
+    #[test]
+    fn dash_offset_wraps() {
+        let line = line();
+        let expected = [(0.0, 1.0), (3.0, 5.0), (7.0, 9.0)];
+        for &offset in &[1.0, 5.0, 41.0, -3.0] {
+            assert!(same(&extents(&dash(&line.segments, false, &[2.0, 2.0], offset)), &expected), "offset {}", offset);
+        }
+    }
+
+    #[test]
+    fn odd_dash_pattern_repeats() {
+        let dashed = |dashes: Vec<f32>| line().stroke_outline(&StrokeStyle { dashes, ..StrokeStyle::new(1.0) }).contours().count();
+        /* 0-3 and 6-9 */
+        assert_eq!(dashed(vec![3.0]), 2);
+        /* 0-1, 3-6 and 7-9, the second time around with on and off swapped */
+        assert_eq!(dashed(vec![1.0, 2.0, 3.0]), 3);
+    }
+
+    #[test]
+    fn dash_across_seam() {
+        let mut square = PathBuilder::new();
+        square.rect(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(dash(&square.segments, true, &[6.0, 4.0], 0.0).len(), 4);
+
+        /* the dash from 38 to 4 along the perimeter continues over the start */
+        let dashes = dash(&square.segments, true, &[6.0, 4.0], 2.0);
+        assert_eq!(dashes.len(), 4);
+        let last = &dashes[dashes.len() - 1];
+        assert!(near(last[0].p1, Vec2::new(0.0, 2.0)) && near(last[last.len() - 1].p3, Vec2::new(4.0, 0.0)));
+        assert!(dashes.iter().all(|dash| (dash.iter().map(|segment| segment.length()).sum::<f32>() - 6.0).abs() < 1.0e-3));
+
+        /* open contours do not wrap around */
+        assert_eq!(dash(&square.segments, false, &[6.0, 4.0], 2.0).len(), 5);
+    }
+
+    #[test]
+    fn invalid_dash_patterns() {
+        let solid = line().stroke_outline(&StrokeStyle::new(1.0)).segments.len();
+        for dashes in [vec![0.0, 0.0], vec![0.0], vec![-1.0, 2.0], vec![2.0, -1.0, 3.0]] {
+            let outline = line().stroke_outline(&StrokeStyle { dashes, ..StrokeStyle::new(1.0) });
+            assert_eq!(outline.segments.len(), solid);
+        }
+    }
+
+    fn bounds_near(path: &Path, min: Vec2, max: Vec2) -> bool {
+        path.bounds().min.distance(min) < 1.0e-2 && path.bounds().max.distance(max) < 1.0e-2
+    }
+
+    #[test]
+    fn offset_rect() {
+        let mut rect = PathBuilder::new();
+        rect.rect(0.0, 0.0, 10.0, 10.0);
+
+        let grown = rect.offset(2.0, LineJoin::Miter).build().unwrap();
+        assert!(bounds_near(&grown, Vec2::new(-2.0, -2.0), Vec2::new(12.0, 12.0)));
+        assert!(grown.contains(Vec2::new(-1.9, -1.9)) && grown.contains(Vec2::new(5.0, 5.0)));
+
+        let rounded = rect.offset(2.0, LineJoin::Round).build().unwrap();
+        assert!(bounds_near(&rounded, Vec2::new(-2.0, -2.0), Vec2::new(12.0, 12.0)));
+        assert!(!rounded.contains(Vec2::new(-1.9, -1.9)) && rounded.contains(Vec2::new(-1.9, 5.0)));
+
+        let shrunk = rect.offset(-2.0, LineJoin::Miter).build().unwrap();
+        assert!(bounds_near(&shrunk, Vec2::new(2.0, 2.0), Vec2::new(8.0, 8.0)));
+        assert!(shrunk.contains(Vec2::new(5.0, 5.0)) && !shrunk.contains(Vec2::new(1.5, 5.0)));
+    }
+
+    #[test]
+    fn offset_circle() {
+        let mut circle = PathBuilder::new();
+        circle.circle(0.0, 0.0, 10.0);
+
+        let grown = circle.offset(2.0, LineJoin::Round).build().unwrap();
+        assert!(bounds_near(&grown, Vec2::new(-12.0, -12.0), Vec2::new(12.0, 12.0)));
+        assert!(grown.contains(Vec2::new(0.0, 11.5)) && !grown.contains(Vec2::new(0.0, 12.5)));
+
+        let shrunk = circle.offset(-2.0, LineJoin::Round).build().unwrap();
+        assert!(bounds_near(&shrunk, Vec2::new(-8.0, -8.0), Vec2::new(8.0, 8.0)));
+        assert!(shrunk.contains(Vec2::new(0.0, 7.5)) && !shrunk.contains(Vec2::new(0.0, 8.5)));
+    }
+
+    #[test]
+    fn offset_collapse() {
+        let mut rect = PathBuilder::new();
+        rect.rect(0.0, 0.0, 10.0, 4.0);
+        assert!(rect.offset(-3.0, LineJoin::Miter).segments.is_empty());
+
+        let mut circle = PathBuilder::new();
+        circle.circle(0.0, 0.0, 10.0);
+        let mut collapsed = circle.offset(-12.0, LineJoin::Round);
+        assert!(collapsed.segments.is_empty());
+        assert_eq!(collapsed.build().err(), Some(PathError::Empty));
+    }
+}
@@ -0,0 +1,753 @@
+use crate::geom::*;
+use crate::path::*;
+use crate::stroke::*;
+use crate::frame::Color;
+
+use std::fmt;
+use xml::reader::{EventReader, XmlEvent};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PathDataError {
+    UnexpectedCharacter(usize),
+    UnexpectedEnd(usize),
+    InvalidNumber(usize),
+    InvalidFlag(usize),
+    MissingMoveTo(usize),
+}
+
+impl PathDataError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            PathDataError::UnexpectedCharacter(offset) |
+            PathDataError::UnexpectedEnd(offset) |
+            PathDataError::InvalidNumber(offset) |
+            PathDataError::InvalidFlag(offset) |
+            PathDataError::MissingMoveTo(offset) => offset,
+        }
+    }
+}
+
+impl fmt::Display for PathDataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match *self {
+            PathDataError::UnexpectedCharacter(_) => "unexpected character",
+            PathDataError::UnexpectedEnd(_) => "unexpected end of path data",
+            PathDataError::InvalidNumber(_) => "invalid number",
+            PathDataError::InvalidFlag(_) => "invalid flag",
+            PathDataError::MissingMoveTo(_) => "path data must begin with a moveto command",
+        };
+        write!(f, "{} at byte {}", message, self.offset())
+    }
+}
+
+impl std::error::Error for PathDataError {}
+
+impl PathBuilder {
+    pub fn svg_path(&mut self, data: &str) -> Result<&mut Self, PathDataError> {
+        PathDataParser {
+            scanner: Scanner::new(data),
+            builder: self,
+            current: Vec2::new(0.0, 0.0),
+            start: Vec2::new(0.0, 0.0),
+            control: None,
+        }.parse()?;
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Control {
+    Cubic(Vec2),
+    Quadratic(Vec2),
+}
+
+struct PathDataParser<'d, 'b> {
+    scanner: Scanner<'d>,
+    builder: &'b mut PathBuilder,
+    current: Vec2,
+    start: Vec2,
+    control: Option<Control>,
+}
+
+impl<'d, 'b> PathDataParser<'d, 'b> {
+    fn parse(&mut self) -> Result<(), PathDataError> {
+        self.scanner.skip_whitespace();
+        match self.scanner.peek() {
+            None => return Ok(()),
+            Some(b'M') | Some(b'm') => {}
+            Some(_) => return Err(PathDataError::MissingMoveTo(self.scanner.position)),
+        }
+
+        while let Some(command) = self.scanner.peek() {
+            if !command.is_ascii_alphabetic() {
+                return Err(PathDataError::UnexpectedCharacter(self.scanner.position));
+            }
+            self.scanner.position += 1;
+            self.command(command)?;
+            self.scanner.skip_whitespace();
+        }
+
+        Ok(())
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), PathDataError> {
+        let relative = command.is_ascii_lowercase();
+        let mut first = true;
+        loop {
+            let origin = if relative { self.current } else { Vec2::new(0.0, 0.0) };
+            let mut control = None;
+            match command.to_ascii_uppercase() {
+                b'M' => {
+                    let point = origin + self.point()?;
+                    if first {
+                        self.builder.move_to(point.x, point.y);
+                        self.start = point;
+                    } else {
+                        self.builder.line_to(point.x, point.y);
+                    }
+                    self.current = point;
+                }
+                b'L' => {
+                    let point = origin + self.point()?;
+                    self.builder.line_to(point.x, point.y);
+                    self.current = point;
+                }
+                b'H' => {
+                    let x = origin.x + self.scanner.number()?;
+                    self.builder.line_to(x, self.current.y);
+                    self.current.x = x;
+                }
+                b'V' => {
+                    let y = origin.y + self.scanner.number()?;
+                    self.builder.line_to(self.current.x, y);
+                    self.current.y = y;
+                }
+                b'C' | b'S' => {
+                    let control1 = if command.eq_ignore_ascii_case(&b'C') {
+                        origin + self.point()?
+                    } else if let Some(Control::Cubic(previous)) = self.control {
+                        2.0 * self.current - previous
+                    } else {
+                        self.current
+                    };
+                    let control2 = origin + self.point()?;
+                    let point = origin + self.point()?;
+                    self.builder.cubic_to(control1.x, control1.y, control2.x, control2.y, point.x, point.y);
+                    self.current = point;
+                    control = Some(Control::Cubic(control2));
+                }
+                b'Q' | b'T' => {
+                    let control1 = if command.eq_ignore_ascii_case(&b'Q') {
+                        origin + self.point()?
+                    } else if let Some(Control::Quadratic(previous)) = self.control {
+                        2.0 * self.current - previous
+                    } else {
+                        self.current
+                    };
+                    let point = origin + self.point()?;
+                    self.builder.quadratic_to(control1.x, control1.y, point.x, point.y);
+                    self.current = point;
+                    control = Some(Control::Quadratic(control1));
+                }
+                b'A' => {
+                    let rx = self.scanner.number()?;
+                    let ry = self.scanner.number()?;
+                    let rotation = self.scanner.number()?;
+                    let large_arc = self.scanner.flag()?;
+                    let sweep = self.scanner.flag()?;
+                    let point = origin + self.point()?;
+                    self.builder.elliptical_arc_to(rx, ry, rotation.to_radians(), large_arc, sweep, point.x, point.y);
+                    self.current = point;
+                }
+                b'Z' => {
+                    self.builder.close();
+                    self.current = self.start;
+                    self.control = None;
+                    return Ok(());
+                }
+                _ => return Err(PathDataError::UnexpectedCharacter(self.scanner.position - 1)),
+            }
+            self.control = control;
+            first = false;
+
+            /* further coordinates after a command repeat it implicitly */
+            self.scanner.skip_separator();
+            if !self.scanner.at_number() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn point(&mut self) -> Result<Vec2, PathDataError> {
+        let x = self.scanner.number()?;
+        let y = self.scanner.number()?;
+        Ok(Vec2::new(x, y))
+    }
+}
+
+struct Scanner<'d> {
+    data: &'d [u8],
+    position: usize,
+}
+
+impl<'d> Scanner<'d> {
+    fn new(data: &'d str) -> Scanner<'d> {
+        Scanner { data: data.as_bytes(), position: 0 }
+    }
+
+    fn number(&mut self) -> Result<f32, PathDataError> {
+        self.skip_separator();
+        let start = self.position;
+        if self.position == self.data.len() {
+            return Err(PathDataError::UnexpectedEnd(start));
+        }
+
+        if let b'+' | b'-' = self.data[self.position] {
+            self.position += 1;
+        }
+        let integer = self.digits();
+        let mut fraction = 0;
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            fraction = self.digits();
+        }
+        if integer == 0 && fraction == 0 {
+            return Err(PathDataError::InvalidNumber(start));
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            /* only consume the exponent if digits follow, so that e.g. "1em" is not misparsed */
+            let mark = self.position;
+            self.position += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.position += 1;
+            }
+            if self.digits() == 0 {
+                self.position = mark;
+            }
+        }
+
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .ok_or(PathDataError::InvalidNumber(start))
+    }
+
+    fn flag(&mut self) -> Result<bool, PathDataError> {
+        self.skip_separator();
+        match self.peek() {
+            Some(b'0') => { self.position += 1; Ok(false) }
+            Some(b'1') => { self.position += 1; Ok(true) }
+            Some(_) => Err(PathDataError::InvalidFlag(self.position)),
+            None => Err(PathDataError::UnexpectedEnd(self.position)),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn at_number(&self) -> bool {
+        matches!(self.peek(), Some(b'0'..=b'9') | Some(b'.') | Some(b'+') | Some(b'-'))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') | Some(b'\x0C') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(b',') {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+    }
+}
+
+pub struct Svg {
+    pub width: f32,
+    pub height: f32,
+    pub items: Vec<SvgItem>,
+}
+
+pub struct SvgItem {
+    pub path: Path,
+    pub transform: Transform,
+    pub color: Color,
+}
+
+#[derive(Debug)]
+pub enum SvgError {
+    Xml(xml::reader::Error),
+    PathData(PathDataError),
+    Path(PathError),
+    InvalidAttribute(String),
+}
+
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvgError::Xml(error) => write!(f, "{}", error),
+            SvgError::PathData(error) => write!(f, "{}", error),
+            SvgError::Path(error) => write!(f, "{}", error),
+            SvgError::InvalidAttribute(name) => write!(f, "invalid value for attribute {}", name),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+#[derive(Clone)]
+struct State {
+    transform: Transform,
+    opacity: f32,
+    fill: Option<Color>,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: Option<Color>,
+    stroke_opacity: f32,
+    stroke_style: StrokeStyle,
+}
+
+impl Svg {
+    pub fn parse(data: &str) -> Result<Svg, SvgError> {
+        let mut svg = Svg { width: 0.0, height: 0.0, items: Vec::new() };
+        let mut stack = vec![State {
+            transform: Transform::id(),
+            opacity: 1.0,
+            fill: Some(Color::rgba(0.0, 0.0, 0.0, 1.0)),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: None,
+            stroke_opacity: 1.0,
+            stroke_style: StrokeStyle::new(1.0),
+        }];
+        let mut root = true;
+        /* depth inside elements whose content is never rendered directly */
+        let mut skip = 0;
+
+        for event in EventReader::from_str(data) {
+            match event.map_err(SvgError::Xml)? {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    if skip > 0 {
+                        skip += 1;
+                        continue;
+                    }
+
+                    let mut attributes: Vec<(String, String)> = attributes
+                        .into_iter()
+                        .map(|attribute| (attribute.name.local_name, attribute.value))
+                        .collect();
+                    if let Some((_, style)) = attributes.iter().find(|(name, _)| name == "style").cloned() {
+                        for declaration in style.split(';') {
+                            let mut parts = declaration.splitn(2, ':');
+                            if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                                attributes.push((name.trim().to_string(), value.trim().to_string()));
+                            }
+                        }
+                    }
+                    let attribute = |name: &str| {
+                        attributes.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+                    };
+
+                    let mut state = stack.last().unwrap().clone();
+                    state.apply(&attributes)?;
+
+                    let mut builder = PathBuilder::new();
+                    match name.local_name.as_str() {
+                        "svg" if root => {
+                            svg.viewport(&attribute, &mut state)?;
+                            root = false;
+                        }
+                        "g" => {}
+                        "path" => {
+                            if let Some(data) = attribute("d") {
+                                builder.svg_path(data).map_err(SvgError::PathData)?;
+                            }
+                        }
+                        "rect" => {
+                            let x = length(&attribute, "x", 0.0)?;
+                            let y = length(&attribute, "y", 0.0)?;
+                            let width = length(&attribute, "width", 0.0)?;
+                            let height = length(&attribute, "height", 0.0)?;
+                            let rx = attribute("rx").map(|_| length(&attribute, "rx", 0.0)).transpose()?;
+                            let ry = attribute("ry").map(|_| length(&attribute, "ry", 0.0)).transpose()?;
+                            let (rx, ry) = match (rx, ry) {
+                                (Some(rx), Some(ry)) => (rx, ry),
+                                (Some(r), None) | (None, Some(r)) => (r, r),
+                                (None, None) => (0.0, 0.0),
+                            };
+                            let rx = rx.max(0.0).min(0.5 * width);
+                            let ry = ry.max(0.0).min(0.5 * height);
+                            if width > 0.0 && height > 0.0 {
+                                rect(&mut builder, x, y, width, height, rx, ry);
+                            }
+                        }
+                        "circle" => {
+                            let r = length(&attribute, "r", 0.0)?;
+                            if r > 0.0 {
+                                builder.circle(length(&attribute, "cx", 0.0)?, length(&attribute, "cy", 0.0)?, r);
+                            }
+                        }
+                        "ellipse" => {
+                            let rx = length(&attribute, "rx", 0.0)?;
+                            let ry = length(&attribute, "ry", 0.0)?;
+                            if rx > 0.0 && ry > 0.0 {
+                                builder.ellipse(length(&attribute, "cx", 0.0)?, length(&attribute, "cy", 0.0)?, rx, ry);
+                            }
+                        }
+                        "line" => {
+                            builder.move_to(length(&attribute, "x1", 0.0)?, length(&attribute, "y1", 0.0)?);
+                            builder.line_to(length(&attribute, "x2", 0.0)?, length(&attribute, "y2", 0.0)?);
+                            state.fill = None;
+                        }
+                        "polyline" | "polygon" => {
+                            let points = attribute("points").unwrap_or("");
+                            let numbers = numbers(points).ok_or_else(|| SvgError::InvalidAttribute("points".to_string()))?;
+                            for (i, point) in numbers.chunks_exact(2).enumerate() {
+                                if i == 0 {
+                                    builder.move_to(point[0], point[1]);
+                                } else {
+                                    builder.line_to(point[0], point[1]);
+                                }
+                            }
+                            if name.local_name == "polygon" && numbers.len() >= 2 {
+                                builder.close();
+                            }
+                        }
+                        "defs" | "symbol" | "clipPath" | "mask" | "pattern" | "marker" |
+                        "linearGradient" | "radialGradient" | "style" | "title" | "desc" | "metadata" => {
+                            skip = 1;
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    state.emit(&mut builder, &mut svg.items)?;
+                    stack.push(state);
+                }
+                XmlEvent::EndElement { .. } => {
+                    if skip > 0 {
+                        skip -= 1;
+                    } else {
+                        stack.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(svg)
+    }
+
+    fn viewport<'a>(&mut self, attribute: &dyn Fn(&str) -> Option<&'a str>, state: &mut State) -> Result<(), SvgError> {
+        let view_box = attribute("viewBox")
+            .map(|view_box| numbers(view_box).filter(|numbers| numbers.len() == 4).ok_or_else(|| SvgError::InvalidAttribute("viewBox".to_string())))
+            .transpose()?;
+
+        self.width = length(attribute, "width", view_box.as_ref().map_or(0.0, |view_box| view_box[2]))?;
+        self.height = length(attribute, "height", view_box.as_ref().map_or(0.0, |view_box| view_box[3]))?;
+
+        if let Some(view_box) = view_box {
+            if view_box[2] > 0.0 && view_box[3] > 0.0 {
+                /* preserveAspectRatio="xMidYMid meet" */
+                let scale = (self.width / view_box[2]).min(self.height / view_box[3]);
+                let offset = Vec2::new(
+                    0.5 * (self.width - scale * view_box[2]) - scale * view_box[0],
+                    0.5 * (self.height - scale * view_box[3]) - scale * view_box[1],
+                );
+                state.transform *= Transform::translate(offset.x, offset.y) * Transform::scale(scale, scale);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl State {
+    fn apply(&mut self, attributes: &[(String, String)]) -> Result<(), SvgError> {
+        for (name, value) in attributes {
+            let value = value.as_str();
+            let invalid = || SvgError::InvalidAttribute(name.clone());
+            match name.as_str() {
+                "transform" => {
+                    self.transform *= transform(value).ok_or_else(invalid)?;
+                }
+                "opacity" => self.opacity *= number(value).ok_or_else(invalid)?.clamp(0.0, 1.0),
+                "fill" => if let Some(fill) = paint(value) { self.fill = fill; },
+                "fill-opacity" => self.fill_opacity = number(value).ok_or_else(invalid)?.clamp(0.0, 1.0),
+                "fill-rule" => match value {
+                    "nonzero" => self.fill_rule = FillRule::NonZero,
+                    "evenodd" => self.fill_rule = FillRule::EvenOdd,
+                    _ => {}
+                },
+                "stroke" => if let Some(stroke) = paint(value) { self.stroke = stroke; },
+                "stroke-opacity" => self.stroke_opacity = number(value).ok_or_else(invalid)?.clamp(0.0, 1.0),
+                "stroke-width" => self.stroke_style.width = number(value).ok_or_else(invalid)?,
+                "stroke-miterlimit" => self.stroke_style.miter_limit = number(value).ok_or_else(invalid)?,
+                "stroke-linejoin" => match value {
+                    "miter" => self.stroke_style.join = LineJoin::Miter,
+                    "round" => self.stroke_style.join = LineJoin::Round,
+                    "bevel" => self.stroke_style.join = LineJoin::Bevel,
+                    _ => {}
+                },
+                "stroke-linecap" => match value {
+                    "butt" => self.stroke_style.cap = LineCap::Butt,
+                    "round" => self.stroke_style.cap = LineCap::Round,
+                    "square" => self.stroke_style.cap = LineCap::Square,
+                    _ => {}
+                },
+                "stroke-dasharray" => {
+                    self.stroke_style.dashes = if value == "none" {
+                        Vec::new()
+                    } else {
+                        numbers(value).ok_or_else(invalid)?
+                    };
+                }
+                "stroke-dashoffset" => self.stroke_style.dash_offset = number(value).ok_or_else(invalid)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit(&self, builder: &mut PathBuilder, items: &mut Vec<SvgItem>) -> Result<(), SvgError> {
+        if builder.segments.is_empty() {
+            return Ok(());
+        }
+
+        /* shapes without area, like a fill on a straight line, are simply not drawn */
+        let mut push = |path: Result<Path, PathError>, color: Color| {
+            match path {
+                Ok(path) => items.push(SvgItem { path, transform: self.transform, color }),
+                Err(PathError::Empty) => {}
+                Err(error) => return Err(SvgError::Path(error)),
+            }
+            Ok(())
+        };
+
+        if let Some(mut color) = self.fill {
+            color.a *= self.fill_opacity * self.opacity;
+            builder.fill_rule(self.fill_rule);
+            push(builder.build(), color)?;
+        }
+
+        if let Some(mut color) = self.stroke {
+            if self.stroke_style.width > 0.0 {
+                color.a *= self.stroke_opacity * self.opacity;
+                push(builder.stroke(&self.stroke_style), color)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/* corners with distinct radii, which PathBuilder::rounded_rect does not cover */
+fn rect(builder: &mut PathBuilder, x: f32, y: f32, width: f32, height: f32, rx: f32, ry: f32) {
+    builder
+        .move_to(x + rx, y)
+        .line_to(x + width - rx, y)
+        .elliptical_arc_to(rx, ry, 0.0, false, true, x + width, y + ry)
+        .line_to(x + width, y + height - ry)
+        .elliptical_arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height)
+        .line_to(x + rx, y + height)
+        .elliptical_arc_to(rx, ry, 0.0, false, true, x, y + height - ry)
+        .line_to(x, y + ry)
+        .elliptical_arc_to(rx, ry, 0.0, false, true, x + rx, y)
+        .close();
+}
+
+fn transform(value: &str) -> Option<Transform> {
+    let mut scanner = Scanner::new(value);
+    let mut result = Transform::id();
+    loop {
+        scanner.skip_separator();
+        if scanner.peek().is_none() {
+            return Some(result);
+        }
+
+        let start = scanner.position;
+        while scanner.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            scanner.position += 1;
+        }
+        let name = &value[start..scanner.position];
+        scanner.skip_whitespace();
+        if scanner.peek() != Some(b'(') {
+            return None;
+        }
+        scanner.position += 1;
+
+        let mut args = Vec::new();
+        loop {
+            scanner.skip_separator();
+            if scanner.peek() == Some(b')') {
+                scanner.position += 1;
+                break;
+            }
+            args.push(scanner.number().ok()?);
+        }
+
+        let transform = match (name, args.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, c, b, d, e, f),
+            ("translate", &[x]) => Transform::translate(x, 0.0),
+            ("translate", &[x, y]) => Transform::translate(x, y),
+            ("scale", &[s]) => Transform::scale(s, s),
+            ("scale", &[x, y]) => Transform::scale(x, y),
+            ("rotate", &[angle]) => rotation(angle),
+            ("rotate", &[angle, x, y]) => Transform::translate(x, y) * rotation(angle) * Transform::translate(-x, -y),
+            ("skewX", &[angle]) => Transform::skew(angle.to_radians(), 0.0),
+            ("skewY", &[angle]) => Transform::skew(0.0, angle.to_radians()),
+            _ => return None,
+        };
+        result *= transform;
+    }
+}
+
+/* svg angles turn the x axis towards the y axis */
+fn rotation(degrees: f32) -> Transform {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Transform::new(cos, -sin, sin, cos, 0.0, 0.0)
+}
+
+fn length<'a>(attribute: &dyn Fn(&str) -> Option<&'a str>, name: &str, default: f32) -> Result<f32, SvgError> {
+    match attribute(name) {
+        Some(value) => number(value).ok_or_else(|| SvgError::InvalidAttribute(name.to_string())),
+        None => Ok(default),
+    }
+}
+
+/* parses a number, ignoring any unit suffix */
+fn number(value: &str) -> Option<f32> {
+    Scanner::new(value).number().ok()
+}
+
+fn numbers(value: &str) -> Option<Vec<f32>> {
+    let mut scanner = Scanner::new(value);
+    let mut numbers = Vec::new();
+    loop {
+        scanner.skip_separator();
+        if scanner.peek().is_none() {
+            return Some(numbers);
+        }
+        numbers.push(scanner.number().ok()?);
+    }
+}
+
+/* None if the paint is not understood; Some(None) for "none" */
+fn paint(value: &str) -> Option<Option<Color>> {
+    let value = value.trim();
+    if value == "none" || value.starts_with("url(") {
+        return Some(None);
+    }
+
+    if let Some(digits) = value.strip_prefix('#') {
+        let component = |i: usize, len: usize| {
+            u8::from_str_radix(digits.get(i * len..(i + 1) * len)?, 16).ok().map(|c| {
+                if len == 1 { (c * 17) as f32 / 255.0 } else { c as f32 / 255.0 }
+            })
+        };
+        let len = match digits.len() { 3 => 1, 6 => 2, _ => return None };
+        return Some(Some(Color::rgba(component(0, len)?, component(1, len)?, component(2, len)?, 1.0)));
+    }
+
+    if value.starts_with("rgb(") && value.ends_with(')') {
+        let mut components = value[4..value.len() - 1].split(',').map(|component| {
+            let component = component.trim();
+            if let Some(percentage) = component.strip_suffix('%') {
+                percentage.parse::<f32>().ok().map(|c| c / 100.0)
+            } else {
+                component.parse::<f32>().ok().map(|c| c / 255.0)
+            }
+        });
+        let r = components.next()??;
+        let g = components.next()??;
+        let b = components.next()??;
+        return Some(Some(Color::rgba(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), 1.0)));
+    }
+
+    let (r, g, b) = match value {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "lime" => (0, 255, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "navy" => (0, 0, 128),
+        "orange" => (255, 165, 0),
+        "transparent" => return Some(None),
+        _ => return None,
+    };
+    Some(Some(Color::rgba(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(data: &str) -> Vec<(Vec2, Vec2)> {
+        let mut builder = PathBuilder::new();
+        builder.svg_path(data).unwrap();
+        builder.segments.iter().map(|segment| (segment.p1, segment.p3)).collect()
+    }
+
+    fn error(data: &str) -> PathDataError {
+        PathBuilder::new().svg_path(data).err().unwrap()
+    }
+
+    #[test]
+    fn relative_commands_match_absolute() {
+        assert_eq!(endpoints("M10 20 L30 20 L30 30 H10 Z"), endpoints("m10 20 h20 v10 l-20 0 z"));
+    }
+
+    #[test]
+    fn implicit_repetition() {
+        let points = endpoints("M0 0 10 0 10 10");
+        assert_eq!(points, vec![
+            (Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)),
+            (Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0)),
+        ]);
+    }
+
+    #[test]
+    fn compact_numbers() {
+        assert_eq!(endpoints("M.5-.5e1L1e1,2"), vec![(Vec2::new(0.5, -5.0), Vec2::new(10.0, 2.0))]);
+    }
+
+    #[test]
+    fn compact_arc_flags() {
+        let spaced = endpoints("M0 0 a5 5 0 1 1 10 0");
+        assert_eq!(spaced, endpoints("M0,0a5,5,0,1,1,10,0"));
+        assert_eq!(spaced.last().unwrap().1, Vec2::new(10.0, 0.0));
+    }
+
+    #[test]
+    fn error_offsets() {
+        assert_eq!(error("L10 10"), PathDataError::MissingMoveTo(0));
+        assert_eq!(error("M10"), PathDataError::UnexpectedEnd(3));
+        assert_eq!(error("M10 x"), PathDataError::InvalidNumber(4));
+        assert_eq!(error("M0 0 A5 5 0 2 0 10 10"), PathDataError::InvalidFlag(12));
+        assert_eq!(error("M0 0 A5 5 0 1"), PathDataError::UnexpectedEnd(13));
+        assert_eq!(error("M0 0 # 1"), PathDataError::UnexpectedCharacter(5));
+        assert_eq!(error("M0 0 X1 1"), PathDataError::UnexpectedCharacter(5));
+    }
+}
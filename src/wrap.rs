@@ -0,0 +1,165 @@
+use crate::font::*;
+
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+/* a line of laid out text as a byte range, excluding the line break itself */
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Line {
+    pub start: usize,
+    pub end: usize,
+    /* width in pixels, not counting trailing whitespace */
+    pub width: f32,
+    /* ended by wrapping rather than by a line break or the end of the text */
+    pub wrapped: bool,
+}
+
+impl<'a> Font<'a> {
+    /* breaks at newlines and, given a maximum width, at Unicode line break opportunities,
+    splitting words that do not fit on a line of their own between characters */
+    pub fn lines(&self, text: &str, size: f32, options: &LayoutOptions) -> Vec<Line> {
+        let scale = self.scale(size);
+
+        /* where the glyphs after and before each character boundary start and end, measured from the start of the text,
+        shaping each piece between break opportunities once so that trying out a line only takes a subtraction */
+        let mut starts = vec![0.0f32; text.len() + 1];
+        let mut ends = vec![0.0f32; text.len() + 1];
+        let mut base = 0.0;
+        let mut start = 0;
+        for (position, _) in linebreaks(text) {
+            let (glyphs, advance) = self.shape_line(&text[start..position], false, options);
+            let mut end = 0.0f32;
+            let mut glyph = 0;
+            for (i, _) in text[start..position].char_indices() {
+                while glyph < glyphs.len() && glyphs[glyph].cluster < i {
+                    end = end.max(glyphs[glyph].x + glyphs[glyph].advance);
+                    glyph += 1;
+                }
+                starts[start + i] = base + glyphs.get(glyph).map_or(advance, |glyph| glyph.x);
+                ends[start + i] = base + end;
+            }
+            base += advance;
+            starts[position] = base;
+            ends[position] = base;
+            start = position;
+        }
+        let width = |start: usize, end: usize| (ends[start + text[start..end].trim_end().len()] - starts[start]).max(0.0);
+
+        /* lines are shaped as a whole once more for their exact width, including kerning across break opportunities */
+        let line = |start: usize, end: usize, wrapped: bool| {
+            let end = start + text[start..end].trim_end_matches(is_newline).len();
+            Line { start, end, width: scale * self.shape_line(text[start..end].trim_end(), false, options).1, wrapped }
+        };
+
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut last = 0;
+        for (position, opportunity) in linebreaks(text) {
+            if let Some(max_width) = options.max_width {
+                let max = max_width / scale;
+                if width(start, position) > max {
+                    if last > start {
+                        lines.push(line(start, last, true));
+                        start = last;
+                    }
+                    while width(start, position) > max {
+                        /* at least one character per line */
+                        let first = start + text[start..].chars().next().map_or(0, |c| c.len_utf8());
+                        let mut end = first;
+                        for (i, c) in text[first..position].char_indices() {
+                            let next = first + i + c.len_utf8();
+                            if width(start, next) > max {
+                                break;
+                            }
+                            end = next;
+                        }
+                        /* the rest of the word stays on the current line */
+                        if end >= start + text[start..position].trim_end().len() {
+                            break;
+                        }
+                        lines.push(line(start, end, true));
+                        start = end;
+                    }
+                }
+            }
+
+            if opportunity == BreakOpportunity::Mandatory {
+                lines.push(line(start, position, false));
+                start = position;
+            }
+            last = position;
+        }
+
+        /* like splitting on newlines, a trailing newline starts one more, empty line */
+        if lines.is_empty() || text.ends_with(is_newline) {
+            lines.push(Line { start: text.len(), end: text.len(), width: 0.0, wrapped: false });
+        }
+
+        lines
+    }
+}
+
+fn is_newline(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] = include_bytes!("../res/SourceSansPro-Regular.ttf");
+
+    fn ranges(lines: &[Line]) -> Vec<(usize, usize, bool)> {
+        lines.iter().map(|line| (line.start, line.end, line.wrapped)).collect()
+    }
+
+    fn wrapped(max_width: f32) -> LayoutOptions {
+        LayoutOptions { max_width: Some(max_width), ..LayoutOptions::new() }
+    }
+
+    #[test]
+    fn mandatory_breaks() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let lines = font.lines("ab\ncd\r\nef", 16.0, &LayoutOptions::new());
+        assert_eq!(ranges(&lines), vec![(0, 2, false), (3, 5, false), (7, 9, false)]);
+
+        let lines = font.lines("ab\r\n\r\n", 16.0, &LayoutOptions::new());
+        assert_eq!(ranges(&lines), vec![(0, 2, false), (4, 4, false), (6, 6, false)]);
+        assert_eq!(ranges(&font.lines("", 16.0, &LayoutOptions::new())), vec![(0, 0, false)]);
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let word = font.lines("hello", 16.0, &LayoutOptions::new())[0].width;
+        let two = font.lines("hello world", 16.0, &LayoutOptions::new())[0].width;
+
+        /* the space stays at the end of the first line without counting towards its width */
+        let lines = font.lines("hello   world", 16.0, &wrapped(0.5 * (word + two)));
+        assert_eq!(ranges(&lines), vec![(0, 8, true), (8, 13, false)]);
+        assert_eq!(lines[0].width, word);
+
+        /* whitespace alone never wraps */
+        let lines = font.lines("hello          ", 16.0, &wrapped(word + 1.0));
+        assert_eq!(ranges(&lines), vec![(0, 15, false)]);
+        assert_eq!(lines[0].width, word);
+    }
+
+    #[test]
+    fn long_words() {
+        let font = Font::from_bytes(FONT).unwrap();
+        let text = "a abcdefghijklmnopqrstuvwxyz";
+        let max_width = font.lines("abcdefgh", 16.0, &LayoutOptions::new())[0].width;
+        let lines = font.lines(text, 16.0, &wrapped(max_width));
+        assert!(lines.len() > 3);
+        assert_eq!(ranges(&lines)[0], (0, 2, true));
+        for pair in lines.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(lines.last().unwrap().end, text.len());
+        assert!(lines.iter().all(|line| line.width <= max_width + 1.0e-3));
+
+        /* a single character wider than the line still gets a line of its own */
+        let lines = font.lines("WW", 16.0, &wrapped(1.0));
+        assert_eq!(ranges(&lines), vec![(0, 1, true), (1, 2, false)]);
+    }
+}